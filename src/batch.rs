@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::helper::VecMap;
+use crate::items::{Item, ItemManager, Recipe, RecipeInput, RecipeLayout};
+use crate::recipes::insert_recipe;
+use crate::registry::{modify, notify_registry_change};
+use crate::resource::ResourceLocation;
+use crate::tags::parse_tag;
+use crate::{ffi_json, read_c_string};
+
+/// 一次批量注册的内容，按物品、标签、配方的顺序注册
+/// - `items` 的每一项与 `Item` 的 JSON 相同，`id` 忽略
+/// - `tags` 为标签名到内容的映射，内容为物品注册名或 `#标签`，任一内容无效时该标签不修改
+/// - `recipes` 的每一项为 `RecipeEntry`
+#[derive(Deserialize)]
+struct Batch {
+    #[serde(default)]
+    items: Vec<Value>,
+    #[serde(default)]
+    tags: VecMap<String, Vec<String>>,
+    #[serde(default)]
+    recipes: Vec<Value>,
+}
+
+/// 批量注册中的配方，物品按注册名引用，`material` 中以 `#` 开头的键为标签
+/// - 数量、概率和布局的检查与 FFI 构建配方时相同
+#[derive(Deserialize)]
+struct RecipeEntry {
+    name: ResourceLocation,
+    #[serde(default)]
+    material: VecMap<String, u64>,
+    products: VecMap<String, u64>,
+    #[serde(default)]
+    timecost: u64,
+    #[serde(default)]
+    category: Option<ResourceLocation>,
+    #[serde(default)]
+    catalysts: Vec<String>,
+    #[serde(default)]
+    product_chances: VecMap<String, f64>,
+    #[serde(default)]
+    unconsumed: Vec<String>,
+    #[serde(default)]
+    layout: Option<LayoutEntry>,
+}
+
+/// 有序配方的网格布局，`slots` 按行优先排列，内容为物品注册名、`#标签` 或 `null`
+#[derive(Deserialize)]
+struct LayoutEntry {
+    width: u32,
+    height: u32,
+    slots: Vec<Option<String>>,
+}
+
+/// 单个条目的注册结果，成功时 `id` 为分配的ID，失败时 `error` 为原因
+#[derive(Serialize)]
+pub struct EntryResult {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl EntryResult {
+    fn new(name: String, result: Result<Option<u32>, String>) -> Self {
+        match result {
+            Ok(id) => Self {
+                name,
+                id,
+                error: None,
+            },
+            Err(error) => Self {
+                name,
+                id: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+/// 批量注册的结果，每个条目按输入顺序对应一项
+#[derive(Serialize, Default)]
+pub struct BatchResult {
+    pub items: Vec<EntryResult>,
+    pub tags: Vec<EntryResult>,
+    pub recipes: Vec<EntryResult>,
+}
+
+fn entry_name(value: &Value) -> String {
+    value
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn item_id(items: &ItemManager, name: &str) -> Result<u32, String> {
+    let name = ResourceLocation::parse(name)?;
+    items
+        .id_by_name(&name)
+        .ok_or_else(|| format!("Unknown item name '{}'", name))
+}
+
+/// 解析物品注册名或以 `#` 开头的标签
+fn parse_input(items: &ItemManager, key: &str) -> Result<RecipeInput, String> {
+    if key.starts_with('#') {
+        parse_tag(key).map(RecipeInput::Tag)
+    } else {
+        item_id(items, key).map(RecipeInput::Item)
+    }
+}
+
+fn build_recipe(items: &ItemManager, entry: RecipeEntry) -> Result<Recipe, String> {
+    let mut recipe = Recipe::new();
+    recipe.timecost = entry.timecost;
+    recipe.category = entry.category;
+    for (key, count) in entry.material {
+        match parse_input(items, &key)? {
+            RecipeInput::Item(id) => recipe.add_material(id, count)?,
+            RecipeInput::Tag(tag) => recipe.add_material_tag(tag, count)?,
+        }
+    }
+    for (key, count) in entry.products {
+        recipe.add_product(item_id(items, &key)?, count)?;
+    }
+    for key in &entry.catalysts {
+        recipe.add_catalyst(item_id(items, key)?);
+    }
+    for (key, chance) in entry.product_chances {
+        recipe.set_chance(item_id(items, &key)?, chance)?;
+    }
+    for key in &entry.unconsumed {
+        recipe.set_consumed(item_id(items, key)?, false)?;
+    }
+    if let Some(layout) = entry.layout {
+        let size = RecipeLayout::size(layout.width, layout.height)?;
+        if layout.slots.len() != size {
+            return Err(format!(
+                "Layout has {} slots (expected {}x{})",
+                layout.slots.len(),
+                layout.width,
+                layout.height
+            ));
+        }
+        recipe.set_layout(layout.width, layout.height)?;
+        for (i, key) in layout.slots.iter().enumerate() {
+            if let Some(key) = key {
+                let i = i as u32;
+                recipe.set_slot(i % layout.width, i / layout.width, parse_input(items, key)?)?;
+            }
+        }
+    }
+    Ok(recipe)
+}
+
+/// 一次注册多个物品、标签和配方，单个条目失败不影响其他条目
+/// - 所有条目在同一次修改中注册，只发布一个新版本的注册表
+/// - 后面的条目可以引用同一批次中前面注册的物品
+pub fn register_batch(json: &str) -> Result<BatchResult, String> {
+    let batch: Batch = serde_json::from_str(json).map_err(|e| format!("Invalid batch: {}", e))?;
+    let mut result = BatchResult::default();
+    modify(|reg| {
+        for value in batch.items {
+            let name = entry_name(&value);
+            let id = serde_json::from_value::<Item>(value)
+                .map_err(|e| format!("Invalid item: {}", e))
+                .and_then(|item| reg.items.insert(item));
+            result.items.push(EntryResult::new(name, id.map(Some)));
+        }
+        for (tag, entries) in batch.tags {
+            let added = parse_tag(&tag).and_then(|tag| {
+                // 先解析所有内容，全部有效时才修改标签
+                let members = entries
+                    .iter()
+                    .map(|entry| match parse_input(reg.items, entry)? {
+                        RecipeInput::Tag(child) if child == tag => {
+                            Err(format!("Tag '#{}' cannot include itself", tag))
+                        }
+                        member => Ok(member),
+                    })
+                    .collect::<Result<Vec<_>, String>>()?;
+                for member in members {
+                    match member {
+                        RecipeInput::Item(id) => reg.tags.add_item(tag.clone(), id),
+                        RecipeInput::Tag(child) => reg.tags.add_tag(tag.clone(), child)?,
+                    }
+                }
+                Ok(None)
+            });
+            result.tags.push(EntryResult::new(tag, added));
+        }
+        for value in batch.recipes {
+            let name = entry_name(&value);
+            let id = serde_json::from_value::<RecipeEntry>(value)
+                .map_err(|e| format!("Invalid recipe: {}", e))
+                .and_then(|entry| {
+                    let name = entry.name.clone();
+                    let recipe = build_recipe(reg.items, entry)?;
+                    insert_recipe(reg.recipes, name, recipe)
+                });
+            result.recipes.push(EntryResult::new(name, id.map(Some)));
+        }
+    });
+    notify_registry_change();
+    Ok(result)
+}
+
+/// 批量注册，`batch` 为 JSON 对象 `{"items": [..], "tags": {..}, "recipes": [..]}`，返回 `BatchResult` 的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_register_batch(batch: *const u8) -> usize {
+    let batch = read_c_string(batch);
+    ffi_json(register_batch(batch))
+}
@@ -0,0 +1,251 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::file::visit_files;
+use crate::items::{ItemManager, Recipe, RecipeInput, RecipeLayout};
+use crate::recipes::insert_recipe;
+use crate::registry::{modify, notify_registry_change};
+use crate::resource::ResourceLocation;
+use crate::tags::parse_tag;
+use crate::{ffi_json, read_c_string};
+
+/// 熔炉类配方未指定 `cookingtime` 时的默认耗时 (ticks)
+const SMELTING_TIME: u64 = 200;
+/// 高炉和烟熏炉的默认耗时 (ticks)
+const FAST_SMELTING_TIME: u64 = 100;
+
+/// 一次导入的统计结果
+#[derive(Serialize, Default)]
+pub struct RecipeImportSummary {
+    /// 成功导入的配方数量
+    pub imported: usize,
+    /// 不支持的配方类型及其出现次数
+    pub unknown_types: BTreeMap<String, usize>,
+    /// 无法导入的配方及原因
+    pub errors: Vec<String>,
+}
+
+/// 从 `.../data/<ns>/recipes/<path>.json` (1.21 起为 `recipe/`) 路径中取出配方名称
+fn recipe_file_name(path: &str) -> Option<ResourceLocation> {
+    let parts: Vec<&str> = path.strip_suffix(".json")?.split('/').collect();
+    let start = parts
+        .windows(3)
+        .position(|w| w[0] == "data" && (w[2] == "recipes" || w[2] == "recipe"))?;
+    let rest = &parts[start + 3..];
+    if rest.is_empty() {
+        return None;
+    }
+    ResourceLocation::parse(&format!("{}:{}", parts[start + 1], rest.join("/"))).ok()
+}
+
+fn item_id(items: &ItemManager, name: &str) -> Result<u32, String> {
+    let name = ResourceLocation::parse(name)?;
+    items
+        .id_by_name(&name)
+        .ok_or_else(|| format!("Unknown item name '{}'", name))
+}
+
+/// 解析原料，支持 `{"item": ..}`、`{"tag": ..}`、`"id"`、`"#tag"` 及其数组
+/// - 数组表示多选一，只使用第一个选项
+/// - 空原料返回 `None`
+fn parse_ingredient(items: &ItemManager, value: &Value) -> Result<Option<RecipeInput>, String> {
+    match value {
+        Value::Array(options) => match options.first() {
+            Some(first) => parse_ingredient(items, first),
+            None => Ok(None),
+        },
+        Value::String(text) if text.starts_with('#') => {
+            Ok(Some(RecipeInput::Tag(parse_tag(text)?)))
+        }
+        Value::String(text) => Ok(Some(RecipeInput::Item(item_id(items, text)?))),
+        Value::Object(map) => {
+            if let Some(tag) = map.get("tag").and_then(Value::as_str) {
+                Ok(Some(RecipeInput::Tag(parse_tag(tag)?)))
+            } else if let Some(item) = map.get("item").and_then(Value::as_str) {
+                Ok(Some(RecipeInput::Item(item_id(items, item)?)))
+            } else {
+                Err(format!("Invalid ingredient {}", value))
+            }
+        }
+        _ => Err(format!("Invalid ingredient {}", value)),
+    }
+}
+
+/// 解析产物，支持 `{"item"/"id": .., "count": ..}` 和 `"id"`，`count` 为旧版放在配方顶层的数量
+fn parse_result(
+    items: &ItemManager,
+    value: &Value,
+    count: Option<u64>,
+) -> Result<(u32, u64), String> {
+    let (name, inner) = match value {
+        Value::String(name) => (name.as_str(), None),
+        Value::Object(map) => {
+            let name = map
+                .get("id")
+                .or_else(|| map.get("item"))
+                .and_then(Value::as_str)
+                .ok_or_else(|| format!("Invalid result {}", value))?;
+            (name, map.get("count").and_then(Value::as_u64))
+        }
+        _ => return Err(format!("Invalid result {}", value)),
+    };
+    Ok((item_id(items, name)?, inner.or(count).unwrap_or(1)))
+}
+
+fn add_ingredient(recipe: &mut Recipe, ingredient: RecipeInput, count: u64) {
+    match ingredient {
+        RecipeInput::Item(id) => recipe.material.add(id, count),
+        RecipeInput::Tag(tag) => {
+            let total = recipe
+                .material_tags
+                .get(&tag)
+                .copied()
+                .unwrap_or(0)
+                .saturating_add(count);
+            recipe.material_tags.insert(tag, total);
+        }
+    }
+}
+
+/// 配方类型对应的类别和工作站，有序和无序合成都归为 `crafting`
+fn category_of(ty: &str) -> (&'static str, &'static str) {
+    match ty {
+        "smelting" => ("minecraft:smelting", "minecraft:furnace"),
+        "blasting" => ("minecraft:blasting", "minecraft:blast_furnace"),
+        "smoking" => ("minecraft:smoking", "minecraft:smoker"),
+        "stonecutting" => ("minecraft:stonecutting", "minecraft:stonecutter"),
+        "smithing" | "smithing_transform" => ("minecraft:smithing", "minecraft:smithing_table"),
+        _ => ("minecraft:crafting", "minecraft:crafting_table"),
+    }
+}
+
+fn field<'a>(json: &'a Value, key: &str) -> Result<&'a Value, String> {
+    json.get(key)
+        .ok_or_else(|| format!("Missing field '{}'", key))
+}
+
+/// 将配方 JSON 转换为 `Recipe`，不支持的类型返回 `Ok(None)`
+fn parse_recipe(items: &ItemManager, json: &Value) -> Result<Option<Recipe>, String> {
+    let ty = field(json, "type")?
+        .as_str()
+        .ok_or("Field 'type' must be a string")?;
+    let ty = ResourceLocation::parse(ty)?;
+    let mut recipe = Recipe::new();
+    let count = json.get("count").and_then(Value::as_u64);
+    let inputs: Vec<&Value> = match (ty.namespace(), ty.path()) {
+        ("minecraft", "crafting_shaped") => {
+            let pattern = field(json, "pattern")?
+                .as_array()
+                .ok_or("Field 'pattern' must be an array")?;
+            let key = field(json, "key")?
+                .as_object()
+                .ok_or("Field 'key' must be an object")?;
+            let rows: Vec<&str> = pattern
+                .iter()
+                .map(|row| row.as_str().ok_or("Pattern rows must be strings"))
+                .collect::<Result<_, _>>()?;
+            let width = rows
+                .iter()
+                .map(|row| row.chars().count())
+                .max()
+                .unwrap_or(0);
+            let mut layout = RecipeLayout::new(
+                width.try_into().unwrap_or(u32::MAX),
+                rows.len().try_into().unwrap_or(u32::MAX),
+            )?;
+            let mut inputs = Vec::new();
+            for (y, row) in rows.iter().enumerate() {
+                for (x, c) in row.chars().enumerate().filter(|&(_, c)| c != ' ') {
+                    let input = key
+                        .get(&c.to_string())
+                        .ok_or_else(|| format!("Pattern key '{}' is not defined", c))?;
+                    layout.slots[y * width + x] = parse_ingredient(items, input)?;
+                    inputs.push(input);
+                }
+            }
+            recipe.layout = Some(layout);
+            inputs
+        }
+        ("minecraft", "crafting_shapeless") => field(json, "ingredients")?
+            .as_array()
+            .ok_or("Field 'ingredients' must be an array")?
+            .iter()
+            .collect(),
+        ("minecraft", "smelting" | "blasting" | "smoking") => {
+            let default = if ty.path() == "smelting" {
+                SMELTING_TIME
+            } else {
+                FAST_SMELTING_TIME
+            };
+            recipe.timecost = json
+                .get("cookingtime")
+                .and_then(Value::as_u64)
+                .unwrap_or(default);
+            vec![field(json, "ingredient")?]
+        }
+        ("minecraft", "stonecutting") => vec![field(json, "ingredient")?],
+        ("minecraft", "smithing" | "smithing_transform") => ["template", "base", "addition"]
+            .into_iter()
+            .filter_map(|key| json.get(key))
+            .collect(),
+        _ => return Ok(None),
+    };
+    let (category, workstation) = category_of(ty.path());
+    recipe.category = Some(ResourceLocation::parse(category)?);
+    if let Some(id) = items.id_by_name(&ResourceLocation::parse(workstation)?) {
+        recipe.add_catalyst(id);
+    }
+    for input in inputs {
+        if let Some(ingredient) = parse_ingredient(items, input)? {
+            add_ingredient(&mut recipe, ingredient, 1);
+        }
+    }
+    let (product, amount) = parse_result(items, field(json, "result")?, count)?;
+    if amount == 0 {
+        return Err("Result count must be non-zero".to_string());
+    }
+    recipe.products.insert(product, amount);
+    Ok(Some(recipe))
+}
+
+/// 从数据包目录或模组 jar/zip 中导入 `data/<ns>/recipes/*.json` 配方
+/// - 不支持的配方类型计入 `unknown_types`，其余配方照常导入
+/// - 引用未注册物品或与已有配方重名的配方记入 `errors`
+pub fn import_recipes(path: &Path) -> Result<RecipeImportSummary, String> {
+    let mut summary = RecipeImportSummary::default();
+    modify(|reg| {
+        visit_files(
+            path,
+            |name| recipe_file_name(name).is_some(),
+            |file, data| {
+                let name = recipe_file_name(file).unwrap();
+                let json = match serde_json::from_slice::<Value>(data) {
+                    Ok(json) => json,
+                    Err(e) => return summary.errors.push(format!("{}: {}", file, e)),
+                };
+                match parse_recipe(reg.items, &json) {
+                    Ok(Some(recipe)) => match insert_recipe(reg.recipes, name, recipe) {
+                        Ok(_) => summary.imported += 1,
+                        Err(e) => summary.errors.push(format!("{}: {}", file, e)),
+                    },
+                    Ok(None) => {
+                        let ty = json["type"].as_str().unwrap_or_default().to_string();
+                        *summary.unknown_types.entry(ty).or_default() += 1;
+                    }
+                    Err(e) => summary.errors.push(format!("{}: {}", file, e)),
+                }
+            },
+        )
+    })?;
+    notify_registry_change();
+    Ok(summary)
+}
+
+/// 从数据包目录或模组 jar/zip 中导入配方，返回 `RecipeImportSummary` 的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_import_recipes(path: *const u8) -> usize {
+    let path = Path::new(read_c_string(path));
+    ffi_json(import_recipes(path))
+}
@@ -0,0 +1,353 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::items::{Item, Recipe};
+use crate::registry::{Registry, notify_registry_change, replace, snapshot};
+use crate::resource::ResourceLocation;
+use crate::tags::Tag;
+use crate::{ffi_bytes, ffi_status, read_bytes};
+
+/// 快照格式版本，客户端只接受相同版本的快照
+pub const SNAPSHOT_FORMAT_VERSION: u32 = 1;
+
+const MAGIC: &[u8; 4] = b"ULRS";
+const KIND_FULL: u8 = 0;
+const KIND_DELTA: u8 = 1;
+/// 头部：`ULRS`、格式版本 (u32)、类型 (u8，0 为完整快照，1 为增量)、
+/// 基准哈希 (u64，完整快照为 0)、结果哈希 (u64)，整数均为小端序
+const HEADER_LEN: usize = 25;
+
+/// 注册表内容，物品按ID排序，配方和标签按名称排序，相同内容的编码和哈希相同
+#[derive(Serialize)]
+struct Contents<'a> {
+    items: Vec<&'a Item>,
+    recipes: Vec<(&'a ResourceLocation, &'a Recipe)>,
+    tags: Vec<(&'a ResourceLocation, &'a Tag)>,
+}
+
+#[derive(Deserialize)]
+struct OwnedContents {
+    items: Vec<Item>,
+    recipes: Vec<(ResourceLocation, Recipe)>,
+    tags: Vec<(ResourceLocation, Tag)>,
+}
+
+/// 两个版本之间的变化，修改过的条目整体替换
+#[derive(Serialize, Deserialize, Default)]
+struct Delta {
+    removed_items: Vec<ResourceLocation>,
+    items: Vec<Item>,
+    removed_recipes: Vec<ResourceLocation>,
+    recipes: Vec<(ResourceLocation, Recipe)>,
+    removed_tags: Vec<ResourceLocation>,
+    tags: Vec<(ResourceLocation, Tag)>,
+}
+
+struct Header {
+    kind: u8,
+    base: u64,
+    hash: u64,
+}
+
+/// 保留的已发送版本数量，更早的版本只能通过完整快照同步
+const MAX_BASELINES: usize = 16;
+
+/// 最近发送给客户端的版本，按哈希查找，每个连接的增量相对于它持有的版本计算
+static BASELINES: Mutex<VecDeque<(u64, Arc<Registry>)>> = Mutex::new(VecDeque::new());
+
+fn contents(registry: &Registry) -> Contents<'_> {
+    let mut items: Vec<&Item> = registry.items.items.iter().collect();
+    items.sort_by_key(|item| item.id);
+    Contents {
+        items,
+        recipes: registry.recipes.recipes().iter().collect(),
+        tags: registry.tags.iter().collect(),
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    postcard::to_allocvec(value).map_err(|e| format!("Failed to encode registry: {}", e))
+}
+
+fn decode<'a, T: Deserialize<'a>>(data: &'a [u8]) -> Result<T, String> {
+    postcard::from_bytes(data).map_err(|e| format!("Failed to decode registry: {}", e))
+}
+
+/// 注册表内容的哈希，用于校验增量的基准和缓存的快照
+pub fn registry_hash(registry: &Registry) -> Result<u64, String> {
+    Ok(xxh3_64(&encode(&contents(registry))?))
+}
+
+fn write_header(kind: u8, base: u64, hash: u64, payload: Vec<u8>) -> Vec<u8> {
+    let mut data = Vec::with_capacity(HEADER_LEN + payload.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&SNAPSHOT_FORMAT_VERSION.to_le_bytes());
+    data.push(kind);
+    data.extend_from_slice(&base.to_le_bytes());
+    data.extend_from_slice(&hash.to_le_bytes());
+    data.extend(payload);
+    data
+}
+
+fn read_header(data: &[u8]) -> Result<(Header, &[u8]), String> {
+    if data.len() < HEADER_LEN || &data[..4] != MAGIC {
+        return Err("Not a registry snapshot".to_string());
+    }
+    let version = u32::from_le_bytes(data[4..8].try_into().unwrap());
+    if version != SNAPSHOT_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported snapshot format version {} (expected {})",
+            version, SNAPSHOT_FORMAT_VERSION
+        ));
+    }
+    let header = Header {
+        kind: data[8],
+        base: u64::from_le_bytes(data[9..17].try_into().unwrap()),
+        hash: u64::from_le_bytes(data[17..25].try_into().unwrap()),
+    };
+    Ok((header, &data[HEADER_LEN..]))
+}
+
+fn diff(base: &Registry, next: &Registry) -> Delta {
+    let mut delta = Delta::default();
+    for item in &base.items.items {
+        if next.items.get_by_name(&item.name).is_none() {
+            delta.removed_items.push(item.name.clone());
+        }
+    }
+    for item in &next.items.items {
+        if base.items.get_by_name(&item.name) != Some(item) {
+            delta.items.push(item.clone());
+        }
+    }
+    for name in base.recipes.recipes().keys() {
+        if !next.recipes.recipes().contains_key(name) {
+            delta.removed_recipes.push(name.clone());
+        }
+    }
+    for (name, recipe) in next.recipes.recipes() {
+        if base.recipes.recipes().get(name) != Some(recipe) {
+            delta.recipes.push((name.clone(), recipe.clone()));
+        }
+    }
+    for (name, _) in base.tags.iter() {
+        if next.tags.get(name).is_none() {
+            delta.removed_tags.push(name.clone());
+        }
+    }
+    for (name, tag) in next.tags.iter() {
+        if base.tags.get(name) != Some(tag) {
+            delta.tags.push((name.clone(), tag.clone()));
+        }
+    }
+    delta
+}
+
+fn apply_delta(registry: &mut Registry, delta: Delta) -> Result<(), String> {
+    // 先移除所有被删除和被修改的物品，修改后的物品可能使用其他物品释放的ID
+    for name in delta
+        .removed_items
+        .iter()
+        .chain(delta.items.iter().map(|item| &item.name))
+    {
+        registry.items.remove(name);
+    }
+    for item in delta.items {
+        registry.items.insert_with_id(item)?;
+    }
+    for name in &delta.removed_recipes {
+        registry.recipes.remove(name);
+    }
+    for (name, recipe) in delta.recipes {
+        insert_recipe(registry, name, recipe)?;
+    }
+    for name in &delta.removed_tags {
+        registry.tags.remove(name)?;
+    }
+    for (name, tag) in delta.tags {
+        insert_tag(registry, name, tag)?;
+    }
+    Ok(())
+}
+
+/// 检查后加入解码得到的配方，物品须已经加入
+fn insert_recipe(
+    registry: &mut Registry,
+    name: ResourceLocation,
+    recipe: Recipe,
+) -> Result<(), String> {
+    recipe
+        .check(&registry.items)
+        .map_err(|e| format!("Invalid recipe '{}': {}", name, e))?;
+    registry.recipes.insert(name, recipe);
+    Ok(())
+}
+
+fn insert_tag(registry: &mut Registry, name: ResourceLocation, tag: Tag) -> Result<(), String> {
+    if let Some(id) = tag
+        .items
+        .iter()
+        .find(|&&id| registry.items.get_by_id(id).is_none())
+    {
+        return Err(format!("Tag '#{}' refers to unknown item id {}", name, id));
+    }
+    if tag.tags.contains(&name) {
+        return Err(format!("Tag '#{}' cannot include itself", name));
+    }
+    registry.tags.set(name, tag);
+    Ok(())
+}
+
+/// 记录发送给客户端的版本，之后的增量可以以它为基准
+fn remember(hash: u64, registry: Arc<Registry>) {
+    let mut baselines = BASELINES.lock();
+    if baselines.iter().any(|(other, _)| *other == hash) {
+        return;
+    }
+    if baselines.len() == MAX_BASELINES {
+        baselines.pop_front();
+    }
+    baselines.push_back((hash, registry));
+}
+
+/// 服务端：编码当前注册表的完整快照，并记录为之后增量的基准
+pub fn encode_snapshot() -> Result<Vec<u8>, String> {
+    let registry = snapshot();
+    let hash = registry_hash(&registry)?;
+    let data = write_header(KIND_FULL, 0, hash, encode(&contents(&registry))?);
+    remember(hash, registry);
+    Ok(data)
+}
+
+/// 服务端：编码从哈希为 `base_hash` 的版本到当前注册表的变化
+/// - `base_hash` 须为之前发送过的快照或增量的结果哈希 (或空注册表的哈希)
+/// - 基准已不在记录中时返回错误，此时应改为发送完整快照
+pub fn encode_delta_from(base_hash: u64) -> Result<Vec<u8>, String> {
+    let base = BASELINES
+        .lock()
+        .iter()
+        .find(|(hash, _)| *hash == base_hash)
+        .map(|(_, base)| base.clone());
+    let base = match base {
+        Some(base) => base,
+        None => {
+            let empty = Registry::new();
+            if registry_hash(&empty)? != base_hash {
+                return Err(format!("Unknown delta base {:016x}", base_hash));
+            }
+            Arc::new(empty)
+        }
+    };
+    let registry = snapshot();
+    let hash = registry_hash(&registry)?;
+    let delta = encode(&diff(&base, &registry))?;
+    remember(hash, registry);
+    Ok(write_header(KIND_DELTA, base_hash, hash, delta))
+}
+
+/// 服务端：编码自最近一次快照或增量以来的变化，之前没有发送过时相对于空注册表
+/// - 只适用于单个客户端，多个客户端应使用 `encode_delta_from`
+pub fn encode_delta() -> Result<Vec<u8>, String> {
+    let latest = BASELINES.lock().back().map(|(hash, _)| *hash);
+    match latest {
+        Some(hash) => encode_delta_from(hash),
+        None => encode_delta_from(registry_hash(&Registry::new())?),
+    }
+}
+
+pub fn reset_baseline() {
+    BASELINES.lock().clear();
+}
+
+/// 客户端：应用完整快照或增量，替换当前注册表
+/// - 增量的基准哈希必须与当前注册表一致，结果的哈希必须与快照中记录的一致，否则注册表不变
+/// - 完整快照在解码前校验哈希，解码得到的物品、配方和标签在加入前逐项检查
+/// - 当前的语言设置保留，ID映射不保留 (物品ID以服务端为准)
+pub fn apply_snapshot(data: &[u8]) -> Result<(), String> {
+    let (header, payload) = read_header(data)?;
+    replace(|current| {
+        let mut next = match header.kind {
+            KIND_FULL => {
+                // 完整快照的内容编码与计算哈希时相同，可以在解码前校验
+                let hash = xxh3_64(payload);
+                if hash != header.hash {
+                    return Err(format!(
+                        "Snapshot hash {:016x} does not match the payload {:016x}",
+                        header.hash, hash
+                    ));
+                }
+                let contents: OwnedContents = decode(payload)?;
+                let mut next = Registry::new();
+                for item in contents.items {
+                    next.items.insert_with_id(item)?;
+                }
+                for (name, recipe) in contents.recipes {
+                    insert_recipe(&mut next, name, recipe)?;
+                }
+                for (name, tag) in contents.tags {
+                    insert_tag(&mut next, name, tag)?;
+                }
+                next
+            }
+            KIND_DELTA => {
+                let hash = registry_hash(current)?;
+                if hash != header.base {
+                    return Err(format!(
+                        "Delta base {:016x} does not match the current registry {:016x}",
+                        header.base, hash
+                    ));
+                }
+                let mut next = current.clone();
+                next.items.id_map = None;
+                apply_delta(&mut next, decode(payload)?)?;
+                next
+            }
+            kind => return Err(format!("Unknown snapshot kind {}", kind)),
+        };
+        let hash = registry_hash(&next)?;
+        if hash != header.hash {
+            return Err(format!(
+                "Snapshot hash {:016x} does not match the decoded registry {:016x}",
+                header.hash, hash
+            ));
+        }
+        next.items.locales = current.items.locales.clone();
+        next.items.rebuild_search();
+        Ok(next)
+    })?;
+    notify_registry_change();
+    Ok(())
+}
+
+/// 编码完整快照，返回数据指针 (由 `ultralightui_free` 释放)，长度写入 `len`
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_registry_snapshot(len: *mut usize) -> usize {
+    ffi_bytes(encode_snapshot(), len)
+}
+
+/// 编码自上次快照或增量以来的变化，返回数据指针 (由 `ultralightui_free` 释放)，长度写入 `len`
+/// - 只适用于单个客户端，见 `ultralightui_registry_delta_from`
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_registry_delta(len: *mut usize) -> usize {
+    ffi_bytes(encode_delta(), len)
+}
+
+/// 编码从客户端持有的版本 (哈希为 `base_hash`) 到当前注册表的变化，基准未知时返回 0
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_registry_delta_from(base_hash: u64, len: *mut usize) -> usize {
+    ffi_bytes(encode_delta_from(base_hash), len)
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_registry_apply(data: *const u8, len: usize) -> u32 {
+    ffi_status(apply_snapshot(read_bytes(data, len)))
+}
+
+/// 当前注册表内容的哈希，可与快照头部的结果哈希比较以判断缓存是否有效
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_registry_hash() -> u64 {
+    registry_hash(&snapshot()).unwrap_or(0)
+}
@@ -1,4 +1,8 @@
-use std::{fs::File, io::BufWriter, path::Path};
+use std::{
+    fs::File,
+    io::{BufWriter, Read},
+    path::Path,
+};
 
 use crate::read_c_string;
 
@@ -18,3 +22,61 @@ extern "C" fn ultralightui_save_to_png(path: *const u8, data: *const u8, width:
     let mut writer = encoder.write_header().unwrap();
     writer.write_image_data(data).unwrap();
 }
+
+/// 遍历目录或 zip/jar 中的文件
+/// - `filter` 和 `f` 的参数为以 `/` 分隔的相对路径，只有通过 `filter` 的文件才会被读取
+pub fn visit_files(
+    path: &Path,
+    filter: impl Fn(&str) -> bool,
+    mut f: impl FnMut(&str, &[u8]),
+) -> Result<(), String> {
+    if path.is_dir() {
+        return visit_dir(path, "", &filter, &mut f);
+    }
+
+    let file =
+        File::open(path).map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Failed to read archive '{}': {}", path.display(), e))?;
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|e| format!("Failed to read archive '{}': {}", path.display(), e))?;
+        if entry.is_dir() || !filter(entry.name()) {
+            continue;
+        }
+        let name = entry.name().to_string();
+        let mut data = Vec::new();
+        entry
+            .read_to_end(&mut data)
+            .map_err(|e| format!("Failed to read '{}' in '{}': {}", name, path.display(), e))?;
+        f(&name, &data);
+    }
+    Ok(())
+}
+
+fn visit_dir(
+    dir: &Path,
+    prefix: &str,
+    filter: &impl Fn(&str) -> bool,
+    f: &mut impl FnMut(&str, &[u8]),
+) -> Result<(), String> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| format!("Failed to read '{}': {}", dir.display(), e))?;
+    let mut entries: Vec<_> = entries
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to read '{}': {}", dir.display(), e))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+        if path.is_dir() {
+            visit_dir(&path, &format!("{}/", name), filter, f)?;
+        } else if filter(&name) {
+            let data = std::fs::read(&path)
+                .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+            f(&name, &data);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,267 @@
+use parking_lot::Mutex;
+
+use crate::items::{Item, ItemType};
+use crate::registry::snapshot;
+use crate::{ffi_status, ffi_string, read_c_string};
+
+/// 能量显示单位，数量始终以 FE 存储
+#[derive(Clone, Copy)]
+pub enum EnergyUnit {
+    FE,
+    RF,
+    /// IC2 的 EU，1 EU = 4 FE
+    EU,
+    /// Mekanism 的焦耳，1 FE = 2.5 J
+    J,
+}
+
+impl EnergyUnit {
+    /// 1 FE 对应的该单位数量
+    fn per_fe(self) -> f64 {
+        match self {
+            EnergyUnit::FE | EnergyUnit::RF => 1.0,
+            EnergyUnit::EU => 0.25,
+            EnergyUnit::J => 2.5,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            EnergyUnit::FE => "FE",
+            EnergyUnit::RF => "RF",
+            EnergyUnit::EU => "EU",
+            EnergyUnit::J => "J",
+        }
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        match text.to_ascii_uppercase().as_str() {
+            "FE" => Ok(EnergyUnit::FE),
+            "RF" => Ok(EnergyUnit::RF),
+            "EU" => Ok(EnergyUnit::EU),
+            "J" => Ok(EnergyUnit::J),
+            _ => Err(format!("Unknown energy unit '{}'", text)),
+        }
+    }
+}
+
+/// 当前使用的能量单位
+static ENERGY_UNIT: Mutex<EnergyUnit> = Mutex::new(EnergyUnit::FE);
+
+/// 数字的千位分隔符和小数点
+#[derive(Clone, Copy)]
+struct Separators {
+    group: char,
+    decimal: char,
+}
+
+/// 按语言代码 (如 `de_de`) 选择分隔符
+fn separators(locale: &str) -> Separators {
+    let language = locale.split('_').next().unwrap_or_default();
+    match language {
+        "de" | "es" | "it" | "nl" | "pt" | "tr" | "id" | "da" => Separators {
+            group: '.',
+            decimal: ',',
+        },
+        "fr" | "ru" | "uk" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "hu" => Separators {
+            group: '\u{a0}',
+            decimal: ',',
+        },
+        _ => Separators {
+            group: ',',
+            decimal: '.',
+        },
+    }
+}
+
+fn group_digits(digits: &str, group: char) -> String {
+    let mut text = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            text.push(group);
+        }
+        text.push(c);
+    }
+    text
+}
+
+fn format_integer(value: u64, separators: Separators) -> String {
+    group_digits(&value.to_string(), separators.group)
+}
+
+/// 最多保留 `decimals` 位小数，去掉末尾的 0
+fn format_decimal(value: f64, decimals: usize, separators: Separators) -> String {
+    let text = format!("{:.*}", decimals, value);
+    let (int, frac) = text.split_once('.').unwrap_or((&text, ""));
+    let frac = frac.trim_end_matches('0');
+    let mut text = group_digits(int, separators.group);
+    if !frac.is_empty() {
+        text.push(separators.decimal);
+        text.push_str(frac);
+    }
+    text
+}
+
+/// 固体：`组数 × 堆叠 + 余数`，不足一组或不可堆叠时只显示数量
+fn format_solid(amount: u64, max_stack: u64, separators: Separators) -> String {
+    if max_stack <= 1 || amount < max_stack {
+        return format_integer(amount, separators);
+    }
+    let (stacks, rest) = (amount / max_stack, amount % max_stack);
+    let stacks = format!(
+        "{} × {}",
+        format_integer(stacks, separators),
+        format_integer(max_stack, separators)
+    );
+    match rest {
+        0 => stacks,
+        rest => format!("{} + {}", stacks, format_integer(rest, separators)),
+    }
+}
+
+/// 按 `decimals` 位小数四舍五入
+fn round(value: f64, decimals: usize) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+/// 按 1000 进位选择前缀级别，返回换算后的值和级别
+/// - 先按该级别显示的小数位数取整再判断，999 999 显示为 1 M 而不是 1 000 k
+fn scale(mut value: f64, levels: usize, decimals: impl Fn(usize) -> usize) -> (f64, usize) {
+    let mut level = 0;
+    while level + 1 < levels && round(value, decimals(level)) >= 1000.0 {
+        value /= 1000.0;
+        level += 1;
+    }
+    (value, level)
+}
+
+const PREFIXES: [&str; 5] = ["", "k", "M", "G", "T"];
+
+/// 液体和气体：数量以 mB 存储，满 1000 mB 时以 B 显示并使用 k/M/G/T 前缀
+fn format_fluid(amount: u64, separators: Separators) -> String {
+    if amount < 1000 {
+        return format!("{} mB", format_integer(amount, separators));
+    }
+    let (value, level) = scale(amount as f64 / 1000.0, PREFIXES.len(), |_| 3);
+    format!(
+        "{} {}B",
+        format_decimal(value, 3, separators),
+        PREFIXES[level]
+    )
+}
+
+/// 能量：按单位换算后使用 k/M/G/T 前缀，没有前缀时显示整数
+fn format_energy(amount: u64, unit: EnergyUnit, separators: Separators) -> String {
+    let decimals = |level| if level == 0 { 0 } else { 2 };
+    let (value, level) = scale(amount as f64 * unit.per_fe(), PREFIXES.len(), decimals);
+    format!(
+        "{} {}{}",
+        format_decimal(value, decimals(level), separators),
+        PREFIXES[level],
+        unit.symbol()
+    )
+}
+
+/// 按物品类型格式化数量
+pub fn format_amount(item: &Item, amount: u64, locale: &str, unit: EnergyUnit) -> String {
+    let separators = separators(locale);
+    match item.ty {
+        ItemType::Solid => format_solid(amount, item.max_stack, separators),
+        ItemType::Fluid | ItemType::Gas => format_fluid(amount, separators),
+        ItemType::Energy => format_energy(amount, unit, separators),
+        ItemType::Placeholder => format_integer(amount, separators),
+    }
+}
+
+/// 按当前语言和能量单位格式化物品数量
+pub fn format_item_amount(id: u32, amount: u64) -> Result<String, String> {
+    let registry = snapshot();
+    let item = registry
+        .items
+        .get_by_id(id)
+        .ok_or_else(|| format!("Unknown item id {}", id))?;
+    Ok(format_amount(
+        item,
+        amount,
+        registry.items.locales.active(),
+        *ENERGY_UNIT.lock(),
+    ))
+}
+
+/// 设置能量单位：`FE`、`RF`、`EU` 或 `J`
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_set_energy_unit(unit: *const u8) -> u32 {
+    let unit = read_c_string(unit);
+    ffi_status(EnergyUnit::parse(unit).map(|unit| *ENERGY_UNIT.lock() = unit))
+}
+
+/// 按物品类型格式化数量，物品不存在时返回 0
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_format_amount(item: u32, amount: u64) -> usize {
+    let text = format_item_amount(item, amount);
+    ffi_string(text.as_deref().map_err(String::clone))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integers_use_locale_separators() {
+        assert_eq!(format_integer(999, separators("en_us")), "999");
+        assert_eq!(format_integer(1_234_567, separators("en_us")), "1,234,567");
+        assert_eq!(format_integer(1_234_567, separators("de_de")), "1.234.567");
+        assert_eq!(
+            format_integer(1_234_567, separators("fr_fr")),
+            "1\u{a0}234\u{a0}567"
+        );
+    }
+
+    #[test]
+    fn decimals_trim_trailing_zeros() {
+        let en = separators("en_us");
+        assert_eq!(format_decimal(1234.5, 2, en), "1,234.5");
+        assert_eq!(format_decimal(2.0, 2, en), "2");
+        assert_eq!(format_decimal(0.125, 3, separators("de_de")), "0,125");
+    }
+
+    #[test]
+    fn solids_show_stacks() {
+        let en = separators("en_us");
+        assert_eq!(format_solid(10, 64, en), "10");
+        assert_eq!(format_solid(128, 64, en), "2 × 64");
+        assert_eq!(format_solid(130, 64, en), "2 × 64 + 2");
+        assert_eq!(format_solid(5, 1, en), "5");
+    }
+
+    #[test]
+    fn fluids_switch_units() {
+        let en = separators("en_us");
+        assert_eq!(format_fluid(999, en), "999 mB");
+        assert_eq!(format_fluid(1500, en), "1.5 B");
+        assert_eq!(format_fluid(1_234_567, en), "1.235 kB");
+        assert_eq!(format_fluid(999_999_999, en), "1 MB");
+    }
+
+    #[test]
+    fn energy_rounds_before_choosing_prefix() {
+        let en = separators("en_us");
+        assert_eq!(format_energy(999, EnergyUnit::FE, en), "999 FE");
+        assert_eq!(format_energy(1500, EnergyUnit::RF, en), "1.5 kRF");
+        assert_eq!(format_energy(999_999, EnergyUnit::FE, en), "1 MFE");
+        assert_eq!(format_energy(999_994, EnergyUnit::FE, en), "999.99 kFE");
+        assert_eq!(format_energy(1000, EnergyUnit::EU, en), "250 EU");
+        assert_eq!(format_energy(400, EnergyUnit::J, en), "1 kJ");
+        assert_eq!(
+            format_energy(1500, EnergyUnit::FE, separators("de_de")),
+            "1,5 kFE"
+        );
+    }
+
+    #[test]
+    fn energy_units_parse_case_insensitively() {
+        assert!(matches!(EnergyUnit::parse("eu"), Ok(EnergyUnit::EU)));
+        assert!(EnergyUnit::parse("mj").is_err());
+    }
+}
@@ -29,6 +29,11 @@ impl<K: PartialEq, V> VecMap<K, V> {
         None
     }
 
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let index = self.items.iter().position(|(k, _)| k == key)?;
+        Some(self.items.remove(index).1)
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
         self.items.iter().map(|(k, v)| (k, v))
     }
@@ -38,6 +43,15 @@ impl<K: PartialEq, V> VecMap<K, V> {
     }
 }
 
+impl<K: PartialEq, V> IntoIterator for VecMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = std::vec::IntoIter<(K, V)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
 impl<K: PartialEq + Debug, V: Debug> Debug for VecMap<K, V> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut dm = f.debug_map();
@@ -127,3 +141,125 @@ impl<'de, K: PartialEq + Deserialize<'de>, V: Deserialize<'de>> Deserialize<'de>
         })
     }
 }
+
+/// 物品ID到数量的集合，按物品ID排序，用于配方和背包的数量计算
+/// - JSON 格式与 `VecMap<u32, u64>` 相同，反序列化时重复的键数量相加
+/// - 所有运算在溢出时饱和
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "VecMap<u32, u64>", into = "VecMap<u32, u64>")]
+pub struct ItemStacks {
+    stacks: Vec<(u32, u64)>,
+}
+
+impl From<VecMap<u32, u64>> for ItemStacks {
+    fn from(entries: VecMap<u32, u64>) -> Self {
+        let mut stacks = ItemStacks::new();
+        for (id, count) in entries {
+            let total = stacks.count(id).saturating_add(count);
+            stacks.insert(id, total);
+        }
+        stacks
+    }
+}
+
+impl From<ItemStacks> for VecMap<u32, u64> {
+    fn from(stacks: ItemStacks) -> Self {
+        VecMap {
+            items: stacks.stacks,
+        }
+    }
+}
+
+impl FromIterator<(u32, u64)> for ItemStacks {
+    fn from_iter<I: IntoIterator<Item = (u32, u64)>>(iter: I) -> Self {
+        let mut stacks = ItemStacks::new();
+        for (id, count) in iter {
+            stacks.add(id, count);
+        }
+        stacks
+    }
+}
+
+impl ItemStacks {
+    pub const fn new() -> Self {
+        Self { stacks: Vec::new() }
+    }
+
+    fn position(&self, id: u32) -> Result<usize, usize> {
+        self.stacks.binary_search_by_key(&id, |&(k, _)| k)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stacks.is_empty()
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        self.position(id).is_ok()
+    }
+
+    /// 物品数量，不存在时为 0
+    pub fn count(&self, id: u32) -> u64 {
+        self.position(id).map_or(0, |i| self.stacks[i].1)
+    }
+
+    /// 设置物品数量，数量为 0 的条目也会保留
+    pub fn insert(&mut self, id: u32, count: u64) {
+        match self.position(id) {
+            Ok(i) => self.stacks[i].1 = count,
+            Err(i) => self.stacks.insert(i, (id, count)),
+        }
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<u64> {
+        let i = self.position(id).ok()?;
+        Some(self.stacks.remove(i).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, u64)> + '_ {
+        self.stacks.iter().copied()
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.stacks.iter().map(|&(id, _)| id)
+    }
+
+    pub fn add(&mut self, id: u32, count: u64) {
+        if count > 0 {
+            self.insert(id, self.count(id).saturating_add(count));
+        }
+    }
+
+    /// 增加物品数量，溢出时返回 `None` 且不修改
+    pub fn checked_add(&mut self, id: u32, count: u64) -> Option<u64> {
+        let total = self.count(id).checked_add(count)?;
+        self.insert(id, total);
+        Some(total)
+    }
+
+    /// 减少最多 `count` 个物品，返回实际减少的数量，减到 0 的条目被移除
+    pub fn sub(&mut self, id: u32, count: u64) -> u64 {
+        let stored = self.count(id);
+        let taken = stored.min(count);
+        if taken == stored {
+            self.remove(id);
+        } else {
+            self.insert(id, stored - taken);
+        }
+        taken
+    }
+
+    pub fn merge(&mut self, other: &ItemStacks) {
+        for (id, count) in other.iter() {
+            self.add(id, count);
+        }
+    }
+}
+
+impl IntoIterator for ItemStacks {
+    type Item = (u32, u64);
+    type IntoIter = std::vec::IntoIter<(u32, u64)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.stacks.into_iter()
+    }
+}
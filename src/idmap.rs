@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use crate::registry::{modify_current, snapshot};
+use crate::resource::ResourceLocation;
+use crate::{ffi_status, read_c_string};
+
+/// ID映射文件格式版本
+pub const ID_MAP_FILE_VERSION: u32 = 1;
+
+/// 持久化的注册名到物品ID映射，使物品ID不随注册顺序变化
+/// - 新名称分配从未使用过的ID，已移除物品的ID作为墓碑保留，不会分配给其他物品
+/// - 墓碑中的名称再次注册时恢复原来的ID
+#[derive(Clone, Serialize, Deserialize)]
+pub struct IdMap {
+    version: u32,
+    /// 下一个分配的ID
+    next_id: u32,
+    ids: BTreeMap<ResourceLocation, u32>,
+    /// 已移除的物品
+    tombstones: BTreeMap<ResourceLocation, u32>,
+}
+
+impl IdMap {
+    pub fn new() -> Self {
+        Self {
+            version: ID_MAP_FILE_VERSION,
+            next_id: 0,
+            ids: BTreeMap::new(),
+            tombstones: BTreeMap::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file =
+            File::open(path).map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+        let map: IdMap = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Failed to parse id map from '{}': {}", path.display(), e))?;
+        if map.version != ID_MAP_FILE_VERSION {
+            return Err(format!(
+                "Unsupported id map file version {} in '{}' (expected {})",
+                map.version,
+                path.display(),
+                ID_MAP_FILE_VERSION
+            ));
+        }
+        let mut errors = Vec::new();
+        let mut seen = BTreeMap::new();
+        for (name, &id) in map.ids.iter().chain(&map.tombstones) {
+            if let Some(other) = seen.insert(id, name) {
+                errors.push(format!(
+                    "Item id {} is assigned to both '{}' and '{}'",
+                    id, other, name
+                ));
+            }
+            if id >= map.next_id {
+                errors.push(format!(
+                    "Item id {} of '{}' is not below next id {}",
+                    id, name, map.next_id
+                ));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(format!(
+                "Invalid id map '{}':\n{}",
+                path.display(),
+                errors.join("\n")
+            ));
+        }
+        Ok(map)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create '{}': {}", path.display(), e))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)
+            .map_err(|e| format!("Failed to write id map to '{}': {}", path.display(), e))
+    }
+
+    /// 返回名称对应的ID，没有记录时分配新ID，ID用尽时返回错误
+    pub fn assign(&mut self, name: &ResourceLocation) -> Result<u32, String> {
+        if let Some(&id) = self.ids.get(name) {
+            return Ok(id);
+        }
+        let id = match self.tombstones.remove(name) {
+            Some(id) => id,
+            None => {
+                let id = self.next_id;
+                self.next_id = id
+                    .checked_add(1)
+                    .ok_or_else(|| format!("Item ids are exhausted, cannot assign '{}'", name))?;
+                id
+            }
+        };
+        self.ids.insert(name.clone(), id);
+        Ok(id)
+    }
+
+    /// 将名称移入墓碑
+    pub fn retire(&mut self, name: &ResourceLocation) {
+        if let Some(id) = self.ids.remove(name) {
+            self.tombstones.insert(name.clone(), id);
+        }
+    }
+
+    /// 将不在 `live` 中的名称移入墓碑
+    pub fn retire_missing(&mut self, live: &BTreeSet<&ResourceLocation>) {
+        let (kept, removed) = std::mem::take(&mut self.ids)
+            .into_iter()
+            .partition(|(name, _)| live.contains(name));
+        self.ids = kept;
+        self.tombstones.extend(removed);
+    }
+}
+
+/// 加载ID映射文件，之后注册的物品按映射分配ID，文件不存在时从空映射开始
+/// - 必须在注册任何物品之前调用
+pub fn load_id_map(path: &Path) -> Result<(), String> {
+    let map = if path.exists() {
+        IdMap::load(path)?
+    } else {
+        IdMap::new()
+    };
+    modify_current(|reg| {
+        if !reg.items.items.is_empty() {
+            return Err("The id map must be loaded before any item is registered".to_string());
+        }
+        reg.items.set_id_map(map);
+        Ok(())
+    })
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_load_id_map(path: *const u8) -> u32 {
+    let path = Path::new(read_c_string(path));
+    ffi_status(load_id_map(path))
+}
+
+/// 保存当前的ID映射，没有加载过映射时失败
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_save_id_map(path: *const u8) -> u32 {
+    let path = Path::new(read_c_string(path));
+    ffi_status(
+        snapshot()
+            .items
+            .id_map
+            .as_ref()
+            .ok_or_else(|| "No id map has been loaded".to_string())
+            .and_then(|map| map.save(path)),
+    )
+}
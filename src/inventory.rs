@@ -0,0 +1,251 @@
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::helper::{ItemStacks, VecMap};
+use crate::items::{ItemManager, Recipe};
+use crate::registry::snapshot;
+use crate::resolver::{CraftPlan, ResolveRequest, resolve_with};
+use crate::resource::ResourceLocation;
+use crate::tags::TagRegistry;
+use crate::{ffi_json, ffi_status, read_c_string};
+
+/// 玩家背包和存储中的物品
+pub type Inventory = ItemStacks;
+
+/// 当前背包和存储中的物品，由宿主推送
+pub static INVENTORY: Mutex<Inventory> = Mutex::new(ItemStacks::new());
+
+/// 可以立即合成的配方
+#[derive(Serialize)]
+pub struct Craftable<'a> {
+    pub name: &'a ResourceLocation,
+    /// 仅使用背包中的物品最多可以合成的次数，`null` 表示配方不消耗原料，不受背包数量限制
+    pub times: Option<u64>,
+}
+
+/// 背包能否同时满足配方 `times` 次的全部原料，同一份物品不会计入多个原料
+/// - 先扣除按物品指定的原料 (不消耗的原料只扣一份)，再为标签原料贪心分配剩余物品
+/// - `tag_inputs` 为标签原料的数量和候选物品，按候选物品从少到多排序
+fn can_craft(
+    recipe: &Recipe,
+    tag_inputs: &[(u64, BTreeSet<u32>)],
+    inventory: &Inventory,
+    times: u64,
+) -> bool {
+    let mut remaining = inventory.clone();
+    for (id, amount) in recipe.material.iter() {
+        let need = match recipe.is_consumed(id) {
+            true => amount.checked_mul(times),
+            false => Some(amount),
+        };
+        match need {
+            Some(need) if remaining.count(id) >= need => {
+                remaining.sub(id, need);
+            }
+            _ => return false,
+        }
+    }
+    for (amount, candidates) in tag_inputs {
+        let Some(mut need) = amount.checked_mul(times) else {
+            return false;
+        };
+        for &id in candidates {
+            need -= remaining.sub(id, need);
+        }
+        if need > 0 {
+            return false;
+        }
+    }
+    true
+}
+
+/// 仅使用背包中的物品，配方最多可以合成的次数
+/// - 按物品和按标签指定的原料共同分配背包中的物品，不消耗的原料只需要一份
+/// - 催化剂和工作站不检查
+/// - 没有消耗任何原料的配方不受背包数量限制，不消耗的原料齐全时返回 `None`，否则返回 `Some(0)`
+pub fn craft_times(recipe: &Recipe, tags: &TagRegistry, inventory: &Inventory) -> Option<u64> {
+    let mut tag_inputs: Vec<(u64, BTreeSet<u32>)> = recipe
+        .material_tags
+        .iter()
+        .map(|(tag, &amount)| (amount, tags.resolve(tag)))
+        .collect();
+    tag_inputs.sort_by_key(|(_, candidates)| candidates.len());
+
+    // 每次合成消耗的物品总数，合成次数不会超过背包物品总数除以它
+    let per_craft = recipe
+        .material
+        .iter()
+        .filter(|&(id, _)| recipe.is_consumed(id))
+        .map(|(_, amount)| amount)
+        .chain(tag_inputs.iter().map(|&(amount, _)| amount))
+        .fold(0u64, u64::saturating_add);
+    if per_craft == 0 {
+        return match can_craft(recipe, &tag_inputs, inventory, 1) {
+            true => None,
+            false => Some(0),
+        };
+    }
+
+    let total = inventory
+        .iter()
+        .map(|(_, count)| count)
+        .fold(0u64, u64::saturating_add);
+    let (mut low, mut high) = (0, total / per_craft);
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        if can_craft(recipe, &tag_inputs, inventory, mid) {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Some(low)
+}
+
+/// 当前可以合成的所有配方，按名称排序，没有任何原料的配方不列出
+pub fn craftable<'a>(
+    recipes: &'a BTreeMap<ResourceLocation, Recipe>,
+    tags: &TagRegistry,
+    inventory: &Inventory,
+) -> Vec<Craftable<'a>> {
+    recipes
+        .iter()
+        .filter(|(_, recipe)| !recipe.material.is_empty() || recipe.material_tags.len() > 0)
+        .filter_map(|(name, recipe)| {
+            let times = craft_times(recipe, tags, inventory);
+            (times != Some(0)).then_some(Craftable { name, times })
+        })
+        .collect()
+}
+
+/// 合成目标物品还缺少什么
+#[derive(Serialize)]
+pub struct MissingReport {
+    /// 背包中不足、需要另外获取的原材料
+    pub missing: ItemStacks,
+    /// 将从背包中使用的物品
+    pub used: ItemStacks,
+    /// 合成树，`from_leftovers` 包括从背包中取出的数量，`leftovers` 包括背包中未使用的物品
+    pub plan: CraftPlan,
+}
+
+/// 在背包物品的基础上求解合成树，背包中已有的中间产物不再合成
+pub fn missing_for(
+    items: &ItemManager,
+    recipes: &BTreeMap<ResourceLocation, Recipe>,
+    tags: &TagRegistry,
+    inventory: &Inventory,
+    request: &ResolveRequest,
+) -> Result<MissingReport, String> {
+    let plan = resolve_with(items, recipes, tags, request, inventory)?;
+    Ok(MissingReport {
+        missing: plan.raw.clone(),
+        used: plan.from_inventory.clone(),
+        plan,
+    })
+}
+
+/// 替换整个背包，`inventory` 为物品ID到数量的 JSON 对象，数量为 0 的条目被忽略
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_inventory_set(inventory: *const u8) -> u32 {
+    let inventory = read_c_string(inventory);
+    ffi_status(
+        serde_json::from_str::<VecMap<u32, u64>>(inventory)
+            .map_err(|e| format!("Invalid inventory: {}", e))
+            .map(|entries| *INVENTORY.lock() = entries.into_iter().collect()),
+    )
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_inventory_add(item: u32, count: u64) {
+    INVENTORY.lock().add(item, count);
+}
+
+/// 从背包中移除物品，返回实际移除的数量
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_inventory_take(item: u32, count: u64) -> u64 {
+    INVENTORY.lock().sub(item, count)
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_inventory_clear() {
+    *INVENTORY.lock() = ItemStacks::new();
+}
+
+/// 返回背包的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_inventory_get() -> usize {
+    ffi_json(Ok(INVENTORY.lock().clone()))
+}
+
+/// 返回当前可以合成的配方 (`Craftable` 数组) 的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_list_craftable() -> usize {
+    let registry = snapshot();
+    let inventory = INVENTORY.lock();
+    ffi_json(Ok(craftable(
+        registry.recipes.recipes(),
+        &registry.tags,
+        &inventory,
+    )))
+}
+
+/// 计算合成目标还缺少的物品，`request` 为 `ResolveRequest` 的 JSON，返回 `MissingReport` 的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_find_missing(request: *const u8) -> usize {
+    let request = read_c_string(request);
+    ffi_json(
+        serde_json::from_str::<ResolveRequest>(request)
+            .map_err(|e| format!("Invalid resolve request: {}", e))
+            .and_then(|request| {
+                let registry = snapshot();
+                let inventory = INVENTORY.lock();
+                missing_for(
+                    &registry.items,
+                    registry.recipes.recipes(),
+                    &registry.tags,
+                    &inventory,
+                    &request,
+                )
+            }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(name: &str) -> ResourceLocation {
+        ResourceLocation::parse(name).unwrap()
+    }
+
+    #[test]
+    fn stacks_are_shared_between_items_and_tags() {
+        let mut tags = TagRegistry::new();
+        tags.add_item(location("planks"), 1);
+        tags.add_item(location("planks"), 2);
+        tags.add_item(location("wood"), 1);
+        let mut recipe = Recipe::new();
+        recipe.add_material(1, 1).unwrap();
+        recipe.add_material_tag(location("planks"), 1).unwrap();
+        recipe.add_material_tag(location("wood"), 1).unwrap();
+        recipe.add_product(3, 1).unwrap();
+        let inventory: Inventory = [(1, 4), (2, 2)].into_iter().collect();
+
+        assert_eq!(craft_times(&recipe, &tags, &inventory), Some(2));
+    }
+
+    #[test]
+    fn unconsumed_only_recipes_are_unlimited() {
+        let mut recipe = Recipe::new();
+        recipe.add_material(1, 1).unwrap();
+        recipe.set_consumed(1, false).unwrap();
+        recipe.add_product(2, 1).unwrap();
+        let tags = TagRegistry::new();
+
+        let inventory: Inventory = [(1, 1)].into_iter().collect();
+        assert_eq!(craft_times(&recipe, &tags, &inventory), None);
+        assert_eq!(craft_times(&recipe, &tags, &Inventory::new()), Some(0));
+    }
+}
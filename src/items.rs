@@ -1,11 +1,23 @@
-use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
-use std::{collections::BTreeMap, fs::File, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+    sync::Arc,
+};
 
-use crate::{helper::VecMap, read_c_string};
+use crate::helper::{ItemStacks, VecMap};
+use crate::idmap::IdMap;
+use crate::locale::{LangLoadSummary, Locales};
+use crate::registry::{modify, notify_registry_change, snapshot};
+use crate::resource::ResourceLocation;
+use crate::tags::TagRegistry;
+use crate::{ffi_json, ffi_status, read_c_string, search::SearchIndex};
 
 #[repr(u8)]
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ItemType {
     Placeholder = 0, // 占位符
     Solid = 1,       // 固体
@@ -14,10 +26,9 @@ pub enum ItemType {
     Energy = 4,      // 能量 (各种电力和魔力)
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct Item {
     /// 物品ID
-    #[serde(skip_deserializing)]
     #[serde(default)]
     pub id: u32,
 
@@ -26,7 +37,7 @@ pub struct Item {
     pub ty: ItemType,
 
     /// 物品名称 (按照 MC 中的注册名)
-    pub name: String,
+    pub name: ResourceLocation,
 
     /// 物品国际化名称
     pub i18n: String,
@@ -41,95 +52,732 @@ pub struct Item {
     pub description: String,
 }
 
-#[derive(Serialize, Deserialize)]
+/// 配方中的一项输入：具体物品或标签
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecipeInput {
+    Item(u32),
+    Tag(ResourceLocation),
+}
+
+/// 有序配方的网格布局，`slots` 按行优先排列，空格为 `None`
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecipeLayout {
+    pub width: u32,
+    pub height: u32,
+    pub slots: Vec<Option<RecipeInput>>,
+}
+
+/// 网格布局的最大边长
+pub const MAX_LAYOUT_SIZE: u32 = 9;
+
+impl RecipeLayout {
+    /// 创建所有格子为空的布局
+    pub fn new(width: u32, height: u32) -> Result<Self, String> {
+        Ok(Self {
+            width,
+            height,
+            slots: vec![None; Self::size(width, height)?],
+        })
+    }
+
+    /// 校验尺寸并返回格子数，宽和高都必须在 1 到 `MAX_LAYOUT_SIZE` 之间
+    pub fn size(width: u32, height: u32) -> Result<usize, String> {
+        let valid = 1..=MAX_LAYOUT_SIZE;
+        width
+            .checked_mul(height)
+            .filter(|_| valid.contains(&width) && valid.contains(&height))
+            .map(|size| size as usize)
+            .ok_or_else(|| {
+                format!(
+                    "Invalid layout size {}x{} (at most {}x{})",
+                    width, height, MAX_LAYOUT_SIZE, MAX_LAYOUT_SIZE
+                )
+            })
+    }
+
+    /// 检查尺寸和格子数是否一致，用于文件或网络中读取的布局
+    pub fn check(&self) -> Result<(), String> {
+        let size = Self::size(self.width, self.height)?;
+        if self.slots.len() != size {
+            return Err(format!(
+                "Layout has {} slots (expected {}x{})",
+                self.slots.len(),
+                self.width,
+                self.height
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct Recipe {
-    #[serde(skip_deserializing)]
     pub id: u32,
     // 配方名称
-    pub name: Option<String>,
+    pub name: Option<ResourceLocation>,
     // 输入
-    pub material: VecMap<u32, u64>,
+    pub material: ItemStacks,
+    // 按标签指定的输入 (标签中任意物品均可)
+    #[serde(default)]
+    pub material_tags: VecMap<ResourceLocation, u64>,
     // 输出
-    pub products: VecMap<u32, u64>,
+    pub products: ItemStacks,
     // 制作时间 (ticks)
     pub timecost: u64,
+    // 配方类别或机器类型 (例如 `minecraft:crafting`、`minecraft:smelting`)
+    #[serde(default)]
+    pub category: Option<ResourceLocation>,
+    // 网格布局 (仅有序配方)
+    #[serde(default)]
+    pub layout: Option<RecipeLayout>,
+    // 催化剂或工作站物品，不消耗
+    #[serde(default)]
+    pub catalysts: Vec<u32>,
+    // 产物的产出概率 (0, 1]，未列出的产物必定产出
+    #[serde(default)]
+    pub product_chances: VecMap<u32, f64>,
+    // 需要但不消耗的输入 (例如工具)，合成后返还
+    #[serde(default)]
+    pub unconsumed: BTreeSet<u32>,
+}
+
+/// 累加一项条目，同一键多次添加时数量相加
+fn add_entry<K: PartialEq + Display>(
+    entries: &mut VecMap<K, u64>,
+    key: K,
+    count: u64,
+) -> Result<(), String> {
+    if count == 0 {
+        return Err(format!("Quantity of {} must be non-zero", key));
+    }
+    let total = entries
+        .get(&key)
+        .map_or(Some(count), |&c| c.checked_add(count))
+        .ok_or_else(|| format!("Quantity of {} overflows", key))?;
+    entries.insert(key, total);
+    Ok(())
+}
+
+/// 累加一项物品条目
+fn add_stack(stacks: &mut ItemStacks, id: u32, count: u64) -> Result<(), String> {
+    if count == 0 {
+        return Err(format!("Quantity of {} must be non-zero", id));
+    }
+    stacks
+        .checked_add(id, count)
+        .map(|_| ())
+        .ok_or_else(|| format!("Quantity of {} overflows", id))
 }
 
+// 修改配方的方法由 FFI 构建器、批量注册和数据包导入共用，检查只写在这里
+impl Recipe {
+    /// 没有任何输入和产物的空配方
+    pub fn new() -> Self {
+        Self {
+            id: 0,
+            name: None,
+            material: ItemStacks::new(),
+            material_tags: VecMap::new(),
+            products: ItemStacks::new(),
+            timecost: 0,
+            category: None,
+            layout: None,
+            catalysts: Vec::new(),
+            product_chances: VecMap::new(),
+            unconsumed: BTreeSet::new(),
+        }
+    }
+
+    pub fn add_material(&mut self, item: u32, count: u64) -> Result<(), String> {
+        add_stack(&mut self.material, item, count)
+    }
+
+    /// 添加标签原料，同一标签多次添加时数量相加
+    pub fn add_material_tag(&mut self, tag: ResourceLocation, count: u64) -> Result<(), String> {
+        add_entry(&mut self.material_tags, tag, count)
+    }
+
+    pub fn add_product(&mut self, item: u32, count: u64) -> Result<(), String> {
+        add_stack(&mut self.products, item, count)
+    }
+
+    /// 设置产物的产出概率 (0, 1]，产物需要先添加，概率为 1 时移除记录
+    pub fn set_chance(&mut self, item: u32, chance: f64) -> Result<(), String> {
+        if !(chance > 0.0 && chance <= 1.0) {
+            return Err(format!("Chance {} must be in (0, 1]", chance));
+        }
+        if !self.products.contains(item) {
+            return Err(format!("Item {} is not a product of this recipe", item));
+        }
+        if chance == 1.0 {
+            self.product_chances.remove(&item);
+        } else {
+            self.product_chances.insert(item, chance);
+        }
+        Ok(())
+    }
+
+    /// 设置原料是否消耗，原料需要先添加
+    pub fn set_consumed(&mut self, item: u32, consumed: bool) -> Result<(), String> {
+        if !self.material.contains(item) {
+            return Err(format!("Item {} is not a material of this recipe", item));
+        }
+        if consumed {
+            self.unconsumed.remove(&item);
+        } else {
+            self.unconsumed.insert(item);
+        }
+        Ok(())
+    }
+
+    /// 添加催化剂，重复添加时忽略
+    pub fn add_catalyst(&mut self, item: u32) {
+        if !self.catalysts.contains(&item) {
+            self.catalysts.push(item);
+        }
+    }
+
+    /// 设置网格布局的尺寸，所有格子清空
+    pub fn set_layout(&mut self, width: u32, height: u32) -> Result<(), String> {
+        self.layout = Some(RecipeLayout::new(width, height)?);
+        Ok(())
+    }
+
+    /// 设置网格中 (`x`, `y`) 格的输入，需要先设置布局
+    pub fn set_slot(&mut self, x: u32, y: u32, input: RecipeInput) -> Result<(), String> {
+        let layout = self
+            .layout
+            .as_mut()
+            .ok_or("Recipe has no layout, set the layout size first")?;
+        if x >= layout.width || y >= layout.height {
+            return Err(format!(
+                "Slot ({}, {}) is outside the {}x{} layout",
+                x, y, layout.width, layout.height
+            ));
+        }
+        layout.slots[(y * layout.width + x) as usize] = Some(input);
+        Ok(())
+    }
+
+    /// 检查从网络读取的配方：数量非零、引用的物品存在，概率、不消耗的原料和布局有效
+    pub fn check(&self, items: &ItemManager) -> Result<(), String> {
+        if self.products.is_empty() {
+            return Err("Recipe has no products".to_string());
+        }
+        let counts = self.material.iter().chain(self.products.iter());
+        if counts
+            .map(|(_, count)| count)
+            .chain(self.material_tags.iter().map(|(_, &count)| count))
+            .any(|count| count == 0)
+        {
+            return Err("Recipe has zero quantities".to_string());
+        }
+        if let Some(id) = self
+            .item_ids()
+            .into_iter()
+            .find(|&id| items.get_by_id(id).is_none())
+        {
+            return Err(format!("Recipe refers to unknown item id {}", id));
+        }
+        for (&id, &chance) in self.product_chances.iter() {
+            let valid = chance > 0.0 && chance <= 1.0 && self.products.contains(id);
+            if !valid {
+                return Err(format!("Invalid chance {} for item id {}", chance, id));
+            }
+        }
+        if let Some(&id) = self
+            .unconsumed
+            .iter()
+            .find(|&&id| !self.material.contains(id))
+        {
+            return Err(format!("Item {} is not a material of this recipe", id));
+        }
+        match &self.layout {
+            Some(layout) => layout.check(),
+            None => Ok(()),
+        }
+    }
+
+    /// 产物的产出概率
+    pub fn chance(&self, item: u32) -> f64 {
+        self.product_chances.get(&item).copied().unwrap_or(1.0)
+    }
+
+    /// 每次合成的期望产量
+    pub fn expected_output(&self, item: u32) -> f64 {
+        self.products.count(item) as f64 * self.chance(item)
+    }
+
+    pub fn is_consumed(&self, item: u32) -> bool {
+        !self.unconsumed.contains(&item)
+    }
+
+    /// 配方引用的所有物品ID (原料、产物、催化剂和布局中的物品)
+    pub fn item_ids(&self) -> BTreeSet<u32> {
+        let mut ids: BTreeSet<u32> = self.material.ids().chain(self.products.ids()).collect();
+        ids.extend(self.catalysts.iter().copied());
+        ids.extend(self.product_chances.iter().map(|(&id, _)| id));
+        ids.extend(self.unconsumed.iter().copied());
+        if let Some(layout) = &self.layout {
+            ids.extend(layout.slots.iter().filter_map(|slot| match slot {
+                Some(RecipeInput::Item(id)) => Some(*id),
+                _ => None,
+            }));
+        }
+        ids
+    }
+}
+
+/// `id` 之后的下一个ID，`id` 已是最大值时返回错误
+fn next_id(id: u32, name: &ResourceLocation) -> Result<u32, String> {
+    id.checked_add(1)
+        .ok_or_else(|| format!("Item id {} for '{}' is out of range", id, name))
+}
+
+/// 按升序插入ID，ID不再按注册顺序分配，索引中的ID不能直接追加
+fn insert_sorted(ids: &mut Vec<u32>, id: u32) {
+    if let Err(i) = ids.binary_search(&id) {
+        ids.insert(i, id);
+    }
+}
+
+#[derive(Clone, Serialize)]
 pub struct ItemManager {
-    pub names: BTreeMap<String, u32>,
-    pub i18ns: BTreeMap<String, u32>,
-    pub l10ns: BTreeMap<String, u32>,
+    /// 按注册顺序排列的物品，ID可能不连续
     pub items: Vec<Item>,
+    /// 物品ID -> `items` 中的下标
+    #[serde(skip)]
+    slots: BTreeMap<u32, usize>,
+    /// 没有ID映射时下一个分配的ID
+    #[serde(skip)]
+    next_id: u32,
+    /// 持久化的ID映射，加载后按注册名分配稳定的ID
+    #[serde(skip)]
+    pub id_map: Option<IdMap>,
+    pub names: BTreeMap<ResourceLocation, u32>,
+    /// 国际化名称索引，同名物品按ID升序排列
+    pub i18ns: BTreeMap<String, Vec<u32>>,
+    /// 本地化名称索引，按当前语言解析后的名称 (`l10n()`) 建立，语言变化时重建
+    /// - 同名物品按ID升序排列，依赖语言所以不写入文件
+    #[serde(skip)]
+    pub l10ns: BTreeMap<String, Vec<u32>>,
+    /// 命名空间索引，每个命名空间下的物品按ID升序排列
+    #[serde(skip)]
+    pub namespaces: BTreeMap<String, Vec<u32>>,
+    #[serde(skip)]
+    pub search: SearchIndex,
+    /// 翻译表在各个快照之间共享，修改时才复制
+    #[serde(skip)]
+    pub locales: Arc<Locales>,
 }
 
 impl ItemManager {
-    pub const fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             items: Vec::new(),
+            slots: BTreeMap::new(),
+            next_id: 0,
+            id_map: None,
             i18ns: BTreeMap::new(),
             l10ns: BTreeMap::new(),
             names: BTreeMap::new(),
+            namespaces: BTreeMap::new(),
+            search: SearchIndex::new(),
+            locales: Arc::new(Locales::new()),
         }
     }
 
-    pub fn insert(&mut self, mut item: Item) -> Result<(), String> {
+    pub fn set_id_map(&mut self, map: IdMap) {
+        self.id_map = Some(map);
+    }
+
+    /// 有ID映射时按注册名分配，否则分配递增的新ID
+    fn allocate_id(&mut self, name: &ResourceLocation) -> Result<u32, String> {
+        let id = match &mut self.id_map {
+            Some(map) => map.assign(name)?,
+            None => self.next_id,
+        };
+        if self.slots.contains_key(&id) {
+            return Err(format!("Item id {} for '{}' is already in use", id, name));
+        }
+        self.next_id = self.next_id.max(next_id(id, name)?);
+        Ok(id)
+    }
+
+    /// 注册物品，返回分配的ID
+    pub fn insert(&mut self, mut item: Item) -> Result<u32, String> {
+        if self.names.contains_key(&item.name) {
+            return Err(format!("Item with name '{}' already exists!", item.name));
+        }
+        item.id = self.allocate_id(&item.name)?;
+        Ok(self.index(item))
+    }
+
+    /// 按物品自带的ID注册，用于复现其他注册表 (如服务端) 中的物品
+    pub fn insert_with_id(&mut self, item: Item) -> Result<u32, String> {
         if self.names.contains_key(&item.name) {
             return Err(format!("Item with name '{}' already exists!", item.name));
         }
-        item.id = self.items.len() as u32;
+        if self.slots.contains_key(&item.id) {
+            return Err(format!(
+                "Item id {} for '{}' is already in use",
+                item.id, item.name
+            ));
+        }
+        self.next_id = self.next_id.max(next_id(item.id, &item.name)?);
+        Ok(self.index(item))
+    }
+
+    fn index(&mut self, item: Item) -> u32 {
         self.names.insert(item.name.clone(), item.id);
-        self.i18ns.insert(item.i18n.clone(), item.id);
-        self.l10ns.insert(item.l10n.clone(), item.id);
+        insert_sorted(
+            self.namespaces
+                .entry(item.name.namespace().to_string())
+                .or_default(),
+            item.id,
+        );
+        insert_sorted(self.i18ns.entry(item.i18n.clone()).or_default(), item.id);
+        let l10n = self.l10n(&item).to_string();
+        self.search.insert(&item, &l10n);
+        insert_sorted(self.l10ns.entry(l10n).or_default(), item.id);
+        let id = item.id;
+        self.slots.insert(id, self.items.len());
         self.items.push(item);
-        Ok(())
+        id
+    }
+
+    /// 移除物品及其所有索引，ID映射中的名称移入墓碑
+    /// - 不检查配方和标签中的引用
+    pub fn remove(&mut self, name: &ResourceLocation) -> Option<Item> {
+        let id = self.names.remove(name)?;
+        let index = self.slots.remove(&id)?;
+        let item = self.items.remove(index);
+        for slot in self.slots.values_mut().filter(|slot| **slot > index) {
+            *slot -= 1;
+        }
+        let l10n = self.l10n(&item).to_string();
+        for (index, key) in [
+            (&mut self.namespaces, item.name.namespace()),
+            (&mut self.i18ns, item.i18n.as_str()),
+            (&mut self.l10ns, l10n.as_str()),
+        ] {
+            if let Some(ids) = index.get_mut(key) {
+                ids.retain(|&other| other != id);
+                if ids.is_empty() {
+                    index.remove(key);
+                }
+            }
+        }
+        self.search.remove(id);
+        if let Some(map) = &mut self.id_map {
+            map.retire(name);
+        }
+        Some(item)
+    }
+
+    /// 按当前语言解析物品名称
+    /// - 依次查找当前语言和回退链中的翻译，然后是注册时的本地化名称，最后是国际化键本身
+    pub fn l10n<'a>(&'a self, item: &'a Item) -> &'a str {
+        if let Some(text) = self.locales.translate(&item.i18n) {
+            text
+        } else if !item.l10n.is_empty() {
+            &item.l10n
+        } else {
+            &item.i18n
+        }
+    }
+
+    /// 翻译表或当前语言变化后重建搜索索引和本地化名称索引
+    pub fn rebuild_search(&mut self) {
+        let mut search = SearchIndex::new();
+        let mut l10ns: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+        for item in &self.items {
+            let l10n = self.l10n(item);
+            search.insert(item, l10n);
+            l10ns.entry(l10n.to_string()).or_default().push(item.id);
+        }
+        for ids in l10ns.values_mut() {
+            ids.sort_unstable();
+        }
+        self.search = search;
+        self.l10ns = l10ns;
+    }
+
+    pub fn load_lang(&mut self, path: &Path) -> Result<LangLoadSummary, String> {
+        let summary = Arc::make_mut(&mut self.locales).load(path)?;
+        self.rebuild_search();
+        Ok(summary)
+    }
+
+    pub fn set_locale(&mut self, locale: &str) {
+        Arc::make_mut(&mut self.locales).set_active(locale);
+        self.rebuild_search();
+    }
+
+    pub fn set_locale_fallback(&mut self, chain: Vec<String>) {
+        Arc::make_mut(&mut self.locales).set_fallback(chain);
+        self.rebuild_search();
     }
 
     pub fn get_by_id(&self, id: u32) -> Option<&Item> {
-        self.items.get(id as usize)
+        self.slots.get(&id).map(|&index| &self.items[index])
     }
 
-    pub fn get_by_name(&self, name: &str) -> Option<&Item> {
+    pub fn get_by_name(&self, name: &ResourceLocation) -> Option<&Item> {
         if let Some(&id) = self.names.get(name) {
             return self.get_by_id(id);
         }
         None
     }
 
-    pub fn get_by_i18n(&self, i18n: &str) -> Option<&Item> {
-        if let Some(&id) = self.i18ns.get(i18n) {
-            return self.get_by_id(id);
+    pub fn id_by_name(&self, name: &ResourceLocation) -> Option<u32> {
+        self.names.get(name).copied()
+    }
+
+    /// 某个命名空间 (模组) 注册的所有物品
+    pub fn ids_by_namespace(&self, namespace: &str) -> &[u32] {
+        self.namespaces.get(namespace).map_or(&[], Vec::as_slice)
+    }
+
+    /// 使用该国际化名称的所有物品，按ID升序排列，同名时第一个即ID最小的物品
+    pub fn ids_by_i18n(&self, i18n: &str) -> &[u32] {
+        self.i18ns.get(i18n).map_or(&[], Vec::as_slice)
+    }
+
+    /// 当前语言下显示为该名称的所有物品，按ID升序排列
+    pub fn ids_by_l10n(&self, l10n: &str) -> &[u32] {
+        self.l10ns.get(l10n).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// 物品注册表文件格式版本
+/// - 版本 2 起索引的每个键对应多个ID
+/// - 版本 3 起不再写入依赖语言的本地化名称索引 (`l10ns`)，加载后按当前语言重建
+pub const ITEMS_FILE_VERSION: u32 = 3;
+
+#[derive(Serialize)]
+struct ItemsFile<'a> {
+    version: u32,
+    #[serde(flatten)]
+    registry: &'a ItemManager,
+}
+
+#[derive(Deserialize)]
+struct ItemsFileData {
+    version: u32,
+    items: Vec<Item>,
+    #[serde(default)]
+    names: VecMap<String, IndexIds>,
+    #[serde(default)]
+    i18ns: VecMap<String, IndexIds>,
+    /// 只出现在版本 3 之前的文件中
+    #[serde(default)]
+    l10ns: VecMap<String, IndexIds>,
+}
+
+/// 索引项，版本 1 的文件中每个键只对应一个ID
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IndexIds {
+    One(u32),
+    Many(Vec<u32>),
+}
+
+impl IndexIds {
+    fn into_vec(self) -> Vec<u32> {
+        match self {
+            IndexIds::One(id) => vec![id],
+            IndexIds::Many(ids) => ids,
         }
-        None
     }
+}
 
-    pub fn get_by_l10n(&self, l10n: &str) -> Option<&Item> {
-        if let Some(&id) = self.l10ns.get(l10n) {
-            return self.get_by_id(id);
+/// 配方注册表文件格式版本
+pub const RECIPES_FILE_VERSION: u32 = 3;
+
+#[derive(Serialize)]
+struct RecipesFile<'a> {
+    version: u32,
+    /// 配方引用的物品ID -> 保存时的注册名，加载时按注册名转换为当前的ID
+    items: BTreeMap<u32, &'a ResourceLocation>,
+    recipes: &'a BTreeMap<ResourceLocation, Recipe>,
+}
+
+#[derive(Deserialize)]
+struct RecipesFileData {
+    version: u32,
+    /// 版本 3 起存在，之前的版本直接使用文件中的ID
+    #[serde(default)]
+    items: BTreeMap<u32, ResourceLocation>,
+    recipes: VecMap<ResourceLocation, Recipe>,
+}
+
+/// 配方表及其反向索引
+#[derive(Clone)]
+pub struct RecipeManager {
+    recipes: BTreeMap<ResourceLocation, Recipe>,
+    /// 物品ID -> 产出该物品的配方
+    sources: BTreeMap<u32, BTreeSet<ResourceLocation>>,
+    /// 物品ID -> 使用该物品 (原料或催化剂) 的配方
+    usages: BTreeMap<u32, BTreeSet<ResourceLocation>>,
+    /// 标签 -> 以该标签为原料的配方
+    tag_usages: BTreeMap<ResourceLocation, BTreeSet<ResourceLocation>>,
+    /// 大于所有已插入配方ID的最小值，用 u64 以便表示 `u32::MAX + 1`
+    next_id: u64,
+}
+
+/// 分页查询结果
+#[derive(Serialize)]
+pub struct RecipePage<'a> {
+    /// 符合条件的配方总数
+    pub total: usize,
+    pub recipes: Vec<&'a ResourceLocation>,
+}
+
+impl<'a> RecipePage<'a> {
+    fn new(
+        names: impl IntoIterator<Item = &'a ResourceLocation>,
+        offset: usize,
+        limit: usize,
+    ) -> Self {
+        let names: Vec<_> = names.into_iter().collect();
+        Self {
+            total: names.len(),
+            recipes: names.into_iter().skip(offset).take(limit).collect(),
         }
-        None
     }
+}
 
-    pub fn id_by_name(&self, name: &str) -> Option<u32> {
-        self.names.get(name).copied()
+impl RecipeManager {
+    pub const fn new() -> Self {
+        Self {
+            recipes: BTreeMap::new(),
+            sources: BTreeMap::new(),
+            usages: BTreeMap::new(),
+            tag_usages: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// 所有配方，按名称排序，修改须通过 `insert` 和 `remove` 以保持索引一致
+    pub fn recipes(&self) -> &BTreeMap<ResourceLocation, Recipe> {
+        &self.recipes
+    }
+
+    /// 下一个新配方的ID，ID用尽时返回错误
+    pub fn next_id(&self) -> Result<u32, String> {
+        u32::try_from(self.next_id).map_err(|_| "Recipe ids are exhausted".to_string())
+    }
+
+    /// 产物为来源，其余引用的物品 (原料、催化剂和布局中的物品) 为用途
+    fn index_keys(recipe: &Recipe) -> (BTreeSet<u32>, BTreeSet<u32>) {
+        let sources = recipe.products.ids().collect();
+        let mut usages: BTreeSet<u32> = recipe
+            .material
+            .ids()
+            .chain(recipe.catalysts.iter().copied())
+            .collect();
+        if let Some(layout) = &recipe.layout {
+            usages.extend(layout.slots.iter().filter_map(|slot| match slot {
+                Some(RecipeInput::Item(id)) => Some(*id),
+                _ => None,
+            }));
+        }
+        (sources, usages)
     }
 
-    pub fn id_by_i18n(&self, i18n: &str) -> Option<u32> {
-        self.i18ns.get(i18n).copied()
+    /// 插入或替换配方并更新索引
+    pub fn insert(&mut self, name: ResourceLocation, recipe: Recipe) {
+        self.remove(&name);
+        self.next_id = self.next_id.max(recipe.id as u64 + 1);
+        let (sources, usages) = Self::index_keys(&recipe);
+        for id in sources {
+            self.sources.entry(id).or_default().insert(name.clone());
+        }
+        for id in usages {
+            self.usages.entry(id).or_default().insert(name.clone());
+        }
+        for (tag, _) in recipe.material_tags.iter() {
+            self.tag_usages
+                .entry(tag.clone())
+                .or_default()
+                .insert(name.clone());
+        }
+        self.recipes.insert(name, recipe);
+    }
+
+    /// 移除配方并更新索引
+    pub fn remove(&mut self, name: &ResourceLocation) -> Option<Recipe> {
+        let recipe = self.recipes.remove(name)?;
+        let (sources, usages) = Self::index_keys(&recipe);
+        for (index, ids) in [(&mut self.sources, sources), (&mut self.usages, usages)] {
+            for id in ids {
+                if let Some(names) = index.get_mut(&id) {
+                    names.remove(name);
+                    if names.is_empty() {
+                        index.remove(&id);
+                    }
+                }
+            }
+        }
+        for (tag, _) in recipe.material_tags.iter() {
+            if let Some(names) = self.tag_usages.get_mut(tag) {
+                names.remove(name);
+                if names.is_empty() {
+                    self.tag_usages.remove(tag);
+                }
+            }
+        }
+        Some(recipe)
+    }
+
+    /// 产出该物品的配方 ("如何合成")
+    pub fn sources(&self, item: u32, offset: usize, limit: usize) -> RecipePage<'_> {
+        RecipePage::new(self.sources.get(&item).into_iter().flatten(), offset, limit)
+    }
+
+    /// 使用该物品的配方 ("用途")，包括通过标签原料间接使用的配方
+    /// 直接引用该物品 (产物、原料、催化剂或布局) 的配方，同时产出和使用的配方只计一次
+    pub fn references(&self, item: u32) -> BTreeSet<&ResourceLocation> {
+        let sources = self.sources.get(&item).into_iter().flatten();
+        let usages = self.usages.get(&item).into_iter().flatten();
+        sources.chain(usages).collect()
+    }
+
+    /// 以该标签为原料的配方数量
+    pub fn tag_users(&self, tag: &ResourceLocation) -> usize {
+        self.tag_usages.get(tag).map_or(0, BTreeSet::len)
     }
 
-    pub fn id_by_l10n(&self, l10n: &str) -> Option<u32> {
-        self.l10ns.get(l10n).copied()
+    pub fn usages(
+        &self,
+        item: u32,
+        tags: &TagRegistry,
+        offset: usize,
+        limit: usize,
+    ) -> RecipePage<'_> {
+        let mut names: BTreeSet<&ResourceLocation> =
+            self.usages.get(&item).into_iter().flatten().collect();
+        for tag in tags.tags_of(item) {
+            names.extend(self.tag_usages.get(tag).into_iter().flatten());
+        }
+        RecipePage::new(names, offset, limit)
     }
 }
 
-static ITEMS: Mutex<ItemManager> = Mutex::new(ItemManager::new());
-static RECIPES: Mutex<BTreeMap<String, Recipe>> = Mutex::new(BTreeMap::new());
+/// 某个命名空间 (模组) 注册的所有配方，按名称排序
+pub fn recipes_in_namespace<'a>(
+    recipes: &'a BTreeMap<ResourceLocation, Recipe>,
+    namespace: &'a str,
+) -> impl Iterator<Item = (&'a ResourceLocation, &'a Recipe)> {
+    recipes
+        .range(ResourceLocation::namespace_start(namespace)..)
+        .take_while(move |(name, _)| name.namespace() == namespace)
+}
 
-pub fn add_item(item: Item) {
-    let mut items = ITEMS.lock();
-    items
-        .insert(item)
-        .unwrap_or_else(|e| eprintln!("Failed to add item: {}", e));
+pub fn add_item(item: Item) -> Result<u32, String> {
+    modify(|reg| reg.items.insert(item))
 }
 
 #[unsafe(no_mangle)]
@@ -140,35 +788,403 @@ extern "C" fn ultralightui_add_item(
     l10n: *const u8,
     max_stack: u64,
     description: *const u8,
-) {
-    let name = read_c_string(name).to_string();
+) -> u32 {
+    let name = read_c_string(name);
     let i18n = read_c_string(i18n).to_string();
     let l10n = read_c_string(l10n).to_string();
     let description = read_c_string(description).to_string();
 
-    add_item(Item {
-        id: 0,
-        ty,
-        name,
-        i18n,
-        l10n,
-        max_stack,
-        description,
+    ffi_status(ResourceLocation::parse(name).and_then(|name| {
+        add_item(Item {
+            id: 0,
+            ty,
+            name,
+            i18n,
+            l10n,
+            max_stack,
+            description,
+        })
+        .map(|_| ())
+    }))
+}
+
+pub fn save_items(path: &Path) -> Result<(), String> {
+    let registry = snapshot();
+    let file =
+        File::create(path).map_err(|e| format!("Failed to create '{}': {}", path.display(), e))?;
+    let data = ItemsFile {
+        version: ITEMS_FILE_VERSION,
+        registry: &registry.items,
+    };
+    serde_json::to_writer_pretty(BufWriter::new(file), &data)
+        .map_err(|e| format!("Failed to write items to '{}': {}", path.display(), e))
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_save_items(path: *const u8) -> u32 {
+    let path = Path::new(read_c_string(path));
+    ffi_status(save_items(path))
+}
+
+pub fn save_recipes(path: &Path) -> Result<(), String> {
+    let registry = snapshot();
+    let mut items = BTreeMap::new();
+    for recipe in registry.recipes.recipes().values() {
+        for id in recipe.item_ids() {
+            if let Some(item) = registry.items.get_by_id(id) {
+                items.insert(id, &item.name);
+            }
+        }
+    }
+    let file =
+        File::create(path).map_err(|e| format!("Failed to create '{}': {}", path.display(), e))?;
+    let data = RecipesFile {
+        version: RECIPES_FILE_VERSION,
+        items,
+        recipes: registry.recipes.recipes(),
+    };
+    serde_json::to_writer_pretty(BufWriter::new(file), &data)
+        .map_err(|e| format!("Failed to write recipes to '{}': {}", path.display(), e))
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_save_recipes(path: *const u8) -> u32 {
+    let path = Path::new(read_c_string(path));
+    ffi_status(save_recipes(path))
+}
+
+/// 检查索引中的每一项都指向键值相符的物品
+fn check_index(
+    errors: &mut Vec<String>,
+    index_name: &str,
+    index: VecMap<String, IndexIds>,
+    ids: &BTreeMap<u32, u32>,
+    manager: &ItemManager,
+    key_of: impl Fn(&Item) -> String,
+) {
+    let mut seen = BTreeSet::new();
+    for (key, entry) in index {
+        if seen.contains(&key) {
+            errors.push(format!("Duplicate key '{}' in index '{}'", key, index_name));
+            continue;
+        }
+        for id in entry.into_vec() {
+            match ids.get(&id).and_then(|&id| manager.get_by_id(id)) {
+                None => errors.push(format!(
+                    "Index '{}' entry '{}' refers to missing item id {}",
+                    index_name, key, id
+                )),
+                Some(item) if key_of(item) != key => errors.push(format!(
+                    "Index '{}' entry '{}' refers to item id {} ('{}') with a different key",
+                    index_name, key, id, item.name
+                )),
+                Some(_) => {}
+            }
+        }
+        seen.insert(key);
+    }
+}
+
+/// 从 `save_items` 写出的文件重建物品注册表
+/// - 文件中的物品ID保持不变，加载过ID映射时按映射分配
+/// - 任何重复或悬空的引用都会导致加载失败，此时注册表保持不变
+/// - 配方和标签按物品ID引用物品，已有配方或标签时拒绝加载，应先加载物品
+pub fn load_items(path: &Path) -> Result<(), String> {
+    let file =
+        File::open(path).map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+    let data: ItemsFileData = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("Failed to parse items from '{}': {}", path.display(), e))?;
+    if !(1..=ITEMS_FILE_VERSION).contains(&data.version) {
+        return Err(format!(
+            "Unsupported items file version {} in '{}' (expected at most {})",
+            data.version,
+            path.display(),
+            ITEMS_FILE_VERSION
+        ));
+    }
+
+    modify(|reg| {
+        if !reg.recipes.recipes().is_empty() || reg.tags.iter().next().is_some() {
+            return Err(format!(
+                "Cannot load items from '{}' while recipes or tags are registered",
+                path.display()
+            ));
+        }
+        replace_items(path, data, reg.items)
+    })?;
+    notify_registry_change();
+    Ok(())
+}
+
+fn replace_items(
+    path: &Path,
+    data: ItemsFileData,
+    current: &mut ItemManager,
+) -> Result<(), String> {
+    let mut errors = Vec::new();
+    let mut manager = ItemManager::new();
+    manager.id_map = current.id_map.clone();
+    let mut ids = BTreeMap::new();
+
+    let mut items = data.items;
+    items.sort_by_key(|item| item.id);
+    for item in items {
+        let old_id = item.id;
+        if ids.contains_key(&old_id) {
+            errors.push(format!("Duplicate item id {} ('{}')", old_id, item.name));
+            continue;
+        }
+        // 有ID映射时按映射分配，否则保留文件中的ID，冲突时才分配新ID
+        let inserted = if manager.id_map.is_some() || manager.get_by_id(old_id).is_some() {
+            manager.insert(item)
+        } else {
+            manager.insert_with_id(item)
+        };
+        match inserted {
+            Ok(id) => {
+                ids.insert(old_id, id);
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    check_index(&mut errors, "names", data.names, &ids, &manager, |item| {
+        item.name.to_string()
     });
+    check_index(&mut errors, "i18ns", data.i18ns, &ids, &manager, |item| {
+        item.i18n.clone()
+    });
+    // 版本 3 之前的文件中保存的 l10ns 以注册时的本地化名称为键，新文件中不应出现
+    if data.version < 3 {
+        check_index(&mut errors, "l10ns", data.l10ns, &ids, &manager, |item| {
+            item.l10n.clone()
+        });
+    } else if data.l10ns.len() > 0 {
+        errors.push(format!(
+            "Index 'l10ns' is not stored in items file version {}",
+            data.version
+        ));
+    }
+
+    if !errors.is_empty() {
+        return Err(format!(
+            "Failed to load items from '{}':\n{}",
+            path.display(),
+            errors.join("\n")
+        ));
+    }
+
+    if let Some(map) = &mut manager.id_map {
+        map.retire_missing(&manager.names.keys().collect());
+    }
+    manager.locales = current.locales.clone();
+    manager.rebuild_search();
+    *current = manager;
+    Ok(())
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_load_items(path: *const u8) -> u32 {
+    let path = Path::new(read_c_string(path));
+    ffi_status(load_items(path))
+}
+
+/// 从 `save_recipes` 写出的文件重建配方表
+/// - 配方中的物品ID按文件中记录的注册名转换为当前的ID，旧版本文件直接对照当前物品表
+/// - 任何重复或悬空的引用都会导致加载失败，此时配方表保持不变
+pub fn load_recipes(path: &Path) -> Result<(), String> {
+    let file =
+        File::open(path).map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+    let data: RecipesFileData = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("Failed to parse recipes from '{}': {}", path.display(), e))?;
+    if !(1..=RECIPES_FILE_VERSION).contains(&data.version) {
+        return Err(format!(
+            "Unsupported recipes file version {} in '{}' (expected at most {})",
+            data.version,
+            path.display(),
+            RECIPES_FILE_VERSION
+        ));
+    }
+
+    modify(|reg| replace_recipes(path, data, reg.items, reg.recipes))?;
+    notify_registry_change();
+    Ok(())
+}
+
+fn replace_recipes(
+    path: &Path,
+    data: RecipesFileData,
+    items: &ItemManager,
+    current: &mut RecipeManager,
+) -> Result<(), String> {
+    let map_id = |id: u32| -> Option<u32> {
+        if data.version < 3 {
+            items.get_by_id(id).map(|item| item.id)
+        } else {
+            items.id_by_name(data.items.get(&id)?)
+        }
+    };
+
+    let mut errors = Vec::new();
+    let mut recipes = RecipeManager::new();
+    let mut recipe_ids = BTreeMap::new();
+
+    for (name, recipe) in data.recipes {
+        if recipes.recipes().contains_key(&name) {
+            errors.push(format!("Duplicate recipe name '{}'", name));
+            continue;
+        }
+        if let Some(other) = recipe_ids.insert(recipe.id, name.clone()) {
+            errors.push(format!(
+                "Duplicate recipe id {} ('{}' and '{}')",
+                recipe.id, other, name
+            ));
+        }
+        // 通过构建器方法重建，多个旧ID映射到同一物品时数量相加
+        let mut mapped = Recipe {
+            material: ItemStacks::new(),
+            material_tags: VecMap::new(),
+            products: ItemStacks::new(),
+            catalysts: Vec::new(),
+            product_chances: VecMap::new(),
+            unconsumed: BTreeSet::new(),
+            ..recipe
+        };
+        let mut result = Ok(());
+        for (id, count) in recipe.material.iter() {
+            result = result.and(match map_id(id) {
+                Some(id) => mapped.add_material(id, count),
+                None => Err(format!("Material refers to missing item id {}", id)),
+            });
+        }
+        for (tag, &count) in recipe.material_tags.iter() {
+            result = result.and(mapped.add_material_tag(tag.clone(), count));
+        }
+        for (id, count) in recipe.products.iter() {
+            result = result.and(match map_id(id) {
+                Some(id) => mapped.add_product(id, count),
+                None => Err(format!("Product refers to missing item id {}", id)),
+            });
+        }
+        for &id in &recipe.catalysts {
+            result = result.and(match map_id(id) {
+                Some(id) => {
+                    mapped.add_catalyst(id);
+                    Ok(())
+                }
+                None => Err(format!("Catalyst refers to missing item id {}", id)),
+            });
+        }
+        for (&id, &chance) in recipe.product_chances.iter() {
+            result = result.and(match map_id(id) {
+                Some(id) => mapped.set_chance(id, chance),
+                None => Err(format!("Chance refers to missing item id {}", id)),
+            });
+        }
+        for &id in &recipe.unconsumed {
+            result = result.and(match map_id(id) {
+                Some(id) => mapped.set_consumed(id, false),
+                None => Err(format!(
+                    "Unconsumed material refers to missing item id {}",
+                    id
+                )),
+            });
+        }
+        if let Some(layout) = &mut mapped.layout {
+            for slot in layout.slots.iter_mut() {
+                if let Some(RecipeInput::Item(id)) = slot {
+                    match map_id(*id) {
+                        Some(new) => *id = new,
+                        None => {
+                            result =
+                                result.and(Err(format!("Layout refers to missing item id {}", id)))
+                        }
+                    }
+                }
+            }
+        }
+        match result.and_then(|()| mapped.check(items)) {
+            Ok(()) => recipes.insert(name, mapped),
+            Err(e) => errors.push(format!("Recipe '{}': {}", name, e)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(format!(
+            "Failed to load recipes from '{}':\n{}",
+            path.display(),
+            errors.join("\n")
+        ));
+    }
+
+    *current = recipes;
+    Ok(())
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_load_recipes(path: *const u8) -> u32 {
+    let path = Path::new(read_c_string(path));
+    ffi_status(load_recipes(path))
+}
+
+/// 查找所有使用该国际化名称的物品，返回ID数组的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_find_items_by_i18n(i18n: *const u8) -> usize {
+    let i18n = read_c_string(i18n);
+    ffi_json(Ok(snapshot().items.ids_by_i18n(i18n)))
+}
+
+/// 查找当前语言下显示为该名称的所有物品，返回ID数组的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_find_items_by_l10n(l10n: *const u8) -> usize {
+    let l10n = read_c_string(l10n);
+    ffi_json(Ok(snapshot().items.ids_by_l10n(l10n)))
+}
+
+/// 列出所有注册过物品的命名空间，返回字符串数组的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_list_namespaces() -> usize {
+    let registry = snapshot();
+    ffi_json(Ok(registry.items.namespaces.keys().collect::<Vec<_>>()))
+}
+
+/// 列出某个命名空间下的物品，返回ID数组的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_list_namespace_items(namespace: *const u8) -> usize {
+    let namespace = read_c_string(namespace);
+    ffi_json(Ok(snapshot().items.ids_by_namespace(namespace)))
+}
+
+/// 列出某个命名空间下的配方，返回配方名称数组的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_list_namespace_recipes(namespace: *const u8) -> usize {
+    let namespace = read_c_string(namespace);
+    let registry = snapshot();
+    let names: Vec<_> = recipes_in_namespace(registry.recipes.recipes(), namespace)
+        .map(|(name, _)| name)
+        .collect();
+    ffi_json(Ok(names))
 }
 
+/// 查询产出该物品的配方，返回 `RecipePage` 的 JSON
 #[unsafe(no_mangle)]
-extern "C" fn ultralightui_save_items(path: *const u8) {
-    let path = Path::new(crate::read_c_string(path));
-    let items = ITEMS.lock();
-    let file = File::create(path).unwrap();
-    // serde_json::to_writer_pretty(file, &*items).unwrap();
+extern "C" fn ultralightui_get_recipe_sources(item: u32, offset: u32, limit: u32) -> usize {
+    let registry = snapshot();
+    ffi_json(Ok(registry.recipes.sources(
+        item,
+        offset as usize,
+        limit as usize,
+    )))
 }
 
+/// 查询使用该物品的配方，返回 `RecipePage` 的 JSON
 #[unsafe(no_mangle)]
-extern "C" fn ultralightui_save_recipes(path: *const u8) {
-    let path = Path::new(crate::read_c_string(path));
-    let recipes = RECIPES.lock();
-    let file = File::create(path).unwrap();
-    serde_json::to_writer_pretty(file, &*recipes).unwrap();
+extern "C" fn ultralightui_get_recipe_usages(item: u32, offset: u32, limit: u32) -> usize {
+    let registry = snapshot();
+    ffi_json(Ok(registry.recipes.usages(
+        item,
+        &registry.tags,
+        offset as usize,
+        limit as usize,
+    )))
 }
@@ -1,86 +1,66 @@
-use std::rc::Rc;
-use ul_next::{
-    Library,
-    app::App,
-    javascript::{JSObject, JSPropertyAttributes, JSValue},
-    platform,
-    window::WindowFlags,
-};
-
-pub struct ModInfo {
-    pub id: String,
-    pub name: String,
-    pub version: String,
-    pub description: String,
-}
-
-fn main() {
-    let lib = Library::linked();
-
-    platform::enable_platform_filesystem(lib.clone(), "./examples").unwrap();
-
-    let app = App::new(lib.clone(), None, None).unwrap();
-
-    let window = app
-        .create_window(
-            900,
-            600,
-            false,
-            WindowFlags {
-                borderless: false,
-                titled: true,
-                resizable: true,
-                maximizable: true,
-                hidden: false,
-            },
-        )
-        .unwrap();
-
-    window.set_title("Javascript example");
-
-    let overlay = window
-        .create_overlay(window.width(), window.height(), 0, 0)
-        .unwrap();
-
-    {
-        let ctx = overlay.view().lock_js_context();
-        let global = ctx.global_object();
-
-        let func = JSObject::new_function_with_callback(&ctx, |ctx, _this, _args| {
-            println!(
-                "Javascript returned {:?}",
-                ctx.evaluate_script("JavascriptCallback();")
-                    .unwrap()
-                    .as_string()
-                    .unwrap()
-            );
-
-            println!(
-                "Javascript returned {:?}",
-                ctx.global_object()
-                    .get_property("JavascriptCallback")
-                    .unwrap()
-                    .as_object()
-                    .unwrap()
-                    .call_as_function(None, &[])
-                    .unwrap()
-                    .as_string()
-                    .unwrap()
-            );
-
-            Ok(JSValue::new_string(ctx, "And Hello from Rust!<br>"))
-        });
-
-        global
-            .set_property("GetRustMessage", &func, JSPropertyAttributes::default())
-            .unwrap();
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Instant;
+use ul_next::View;
+use ul_next::javascript::{JSContext, JSObject, JSPropertyAttributes, JSValue};
+
+use crate::format::format_item_amount;
+use crate::inventory::{INVENTORY, craftable, missing_for};
+use crate::items::Recipe;
+use crate::locale::LocaleInfo;
+use crate::mods::{MODS, list_mods, summarize};
+use crate::query::query_items;
+use crate::registry::snapshot;
+use crate::render::renderer_pending;
+use crate::resolver::{ResolveRequest, resolve};
+use crate::resource::ResourceLocation;
+use crate::search::search_items;
+use crate::tags::parse_tag;
+use crate::validate::validate;
 
-        let mods = JSObject::new(&ctx);
+type JsFunction = for<'c> fn(&'c JSContext, &[JSValue<'c>]) -> Result<JSValue<'c>, JSValue<'c>>;
 
-        global
+/// 暴露给页面的函数，挂在全局对象 `ultralightui` 上
+const API_FUNCTIONS: &[(&str, JsFunction)] = &[
+    ("searchItems", js_search_items),
+    ("queryItems", js_query_items),
+    ("getLocale", js_get_locale),
+    ("translate", js_translate),
+    ("localizeItem", js_localize_item),
+    ("listMods", js_list_mods),
+    ("getMod", js_get_mod),
+    ("listTags", js_list_tags),
+    ("getTagItems", js_get_tag_items),
+    ("getRecipe", js_get_recipe),
+    ("resolveCrafting", js_resolve_crafting),
+    ("getRecipeSources", js_get_recipe_sources),
+    ("getRecipeUsages", js_get_recipe_usages),
+    ("validateRegistry", js_validate_registry),
+    ("getInventory", js_get_inventory),
+    ("listCraftable", js_list_craftable),
+    ("findMissing", js_find_missing),
+    ("formatAmount", js_format_amount),
+];
+
+/// 在视图的每次页面加载时注入 `ultralightui` 全局对象
+pub fn install_api(view: &View) {
+    view.set_window_object_ready_callback(|view, _frame_id, is_main_frame, _url| {
+        if !is_main_frame {
+            return;
+        }
+        let ctx = view.lock_js_context();
+        let api = JSObject::new(&ctx);
+        for &(name, func) in API_FUNCTIONS {
+            let func =
+                JSObject::new_function_with_callback(&ctx, move |ctx, _this, args| func(ctx, args));
+            api.set_property(name, &func, JSPropertyAttributes::default())
+                .unwrap();
+        }
+        ctx.global_object()
             .set_property(
-                "mods",
-                &mods,
+                "ultralightui",
+                &api,
                 JSPropertyAttributes {
                     read_only: true,
                     dont_enum: false,
@@ -88,17 +68,331 @@ fn main() {
                 },
             )
             .unwrap();
+    });
+}
+
+/// 在所有视图的 `window` 上派发一个 `CustomEvent`，`detail` 为 JSON
+pub fn dispatch_event(name: &str, detail: &impl Serialize) {
+    let detail = serde_json::to_string(detail).unwrap_or_else(|_| "null".to_string());
+    let script = format!(
+        "window.dispatchEvent(new CustomEvent({}, {{ detail: {} }}));",
+        serde_json::to_string(name).unwrap(),
+        detail
+    );
+    renderer_pending(move |views, views_updated, _| {
+        for (&id, view) in views.iter() {
+            if let Ok(Err(e)) = view.evaluate_script(&script) {
+                eprintln!("Failed to dispatch event in view {}: {}", id, e);
+            }
+            views_updated.insert(id, Instant::now());
+        }
+        false
+    });
+}
+
+fn arg_string<'c>(
+    ctx: &'c JSContext,
+    args: &[JSValue<'c>],
+    index: usize,
+) -> Result<String, JSValue<'c>> {
+    match args.get(index) {
+        Some(value) if value.is_string() => Ok(value.as_string()?.to_string()),
+        _ => Err(JSValue::new_string(
+            ctx,
+            &format!("Argument {} must be a string", index),
+        )),
+    }
+}
+
+fn arg_number<'c>(
+    ctx: &'c JSContext,
+    args: &[JSValue<'c>],
+    index: usize,
+    default: f64,
+) -> Result<f64, JSValue<'c>> {
+    match args.get(index) {
+        None => Ok(default),
+        Some(value) if value.is_undefined() => Ok(default),
+        Some(value) if value.is_number() => Ok(value.as_number()?),
+        _ => Err(JSValue::new_string(
+            ctx,
+            &format!("Argument {} must be a number", index),
+        )),
+    }
+}
+
+/// 将对象参数经 JSON 转换为 Rust 值
+fn arg_json<'c, T: DeserializeOwned>(
+    ctx: &'c JSContext,
+    args: &[JSValue<'c>],
+    index: usize,
+) -> Result<T, JSValue<'c>> {
+    match args.get(index) {
+        Some(value) if value.is_object() => {
+            let json = value.to_json_string()?.to_string();
+            serde_json::from_str(&json).map_err(|e| {
+                JSValue::new_string(ctx, &format!("Invalid argument {}: {}", index, e))
+            })
+        }
+        _ => Err(JSValue::new_string(
+            ctx,
+            &format!("Argument {} must be an object", index),
+        )),
     }
+}
 
-    overlay.view().load_html("").unwrap();
+/// 将 Rust 值经 JSON 转换为 JS 值
+fn to_js<'c>(ctx: &'c JSContext, value: &impl Serialize) -> Result<JSValue<'c>, JSValue<'c>> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| JSValue::new_string(ctx, &format!("Failed to serialize result: {}", e)))?;
+    JSValue::new_from_json(ctx, &json)
+        .ok_or_else(|| JSValue::new_string(ctx, "Failed to parse result JSON"))
+}
 
-    let app = Rc::new(app);
-    let app_clone = app.clone();
+/// `ultralightui.searchItems(query, limit = 50)`
+fn js_search_items<'c>(
+    ctx: &'c JSContext,
+    args: &[JSValue<'c>],
+) -> Result<JSValue<'c>, JSValue<'c>> {
+    let query = arg_string(ctx, args, 0)?;
+    let limit = arg_number(ctx, args, 1, 50.0)?;
+    let registry = snapshot();
+    to_js(ctx, &search_items(&registry.items, &query, limit as usize))
+}
 
-    window.set_close_callback(move |_window| {
-        app_clone.quit();
-    });
+/// `ultralightui.queryItems(query, limit = 50)`，语法错误时抛出异常
+fn js_query_items<'c>(
+    ctx: &'c JSContext,
+    args: &[JSValue<'c>],
+) -> Result<JSValue<'c>, JSValue<'c>> {
+    let query = arg_string(ctx, args, 0)?;
+    let limit = arg_number(ctx, args, 1, 50.0)?;
+    let registry = snapshot();
+    match query_items(&registry.items, &registry.tags, &query, limit as usize) {
+        Ok(results) => to_js(ctx, &results),
+        Err(e) => Err(to_js(ctx, &e)?),
+    }
+}
+
+/// `ultralightui.getLocale()`
+fn js_get_locale<'c>(
+    ctx: &'c JSContext,
+    _args: &[JSValue<'c>],
+) -> Result<JSValue<'c>, JSValue<'c>> {
+    let registry = snapshot();
+    to_js(ctx, &LocaleInfo::new(&registry.items.locales))
+}
+
+/// `ultralightui.translate(key)`，找不到翻译时返回键本身
+fn js_translate<'c>(ctx: &'c JSContext, args: &[JSValue<'c>]) -> Result<JSValue<'c>, JSValue<'c>> {
+    let key = arg_string(ctx, args, 0)?;
+    let registry = snapshot();
+    Ok(JSValue::new_string(
+        ctx,
+        registry.items.locales.translate(&key).unwrap_or(&key),
+    ))
+}
+
+/// `ultralightui.localizeItem(id)`，物品不存在时返回 `null`
+fn js_localize_item<'c>(
+    ctx: &'c JSContext,
+    args: &[JSValue<'c>],
+) -> Result<JSValue<'c>, JSValue<'c>> {
+    let id = arg_number(ctx, args, 0, -1.0)?;
+    let registry = snapshot();
+    match registry.items.get_by_id(id as u32).filter(|_| id >= 0.0) {
+        Some(item) => Ok(JSValue::new_string(ctx, registry.items.l10n(item))),
+        None => Ok(JSValue::new_null(ctx)),
+    }
+}
 
-    // run the main loop
-    app.run();
+/// `ultralightui.listMods()`
+fn js_list_mods<'c>(ctx: &'c JSContext, _args: &[JSValue<'c>]) -> Result<JSValue<'c>, JSValue<'c>> {
+    let registry = snapshot();
+    let mods = MODS.lock();
+    to_js(
+        ctx,
+        &list_mods(&registry.items, registry.recipes.recipes(), &mods),
+    )
+}
+
+/// `ultralightui.getMod(id)`，模组不存在时返回 `null`
+fn js_get_mod<'c>(ctx: &'c JSContext, args: &[JSValue<'c>]) -> Result<JSValue<'c>, JSValue<'c>> {
+    let id = arg_string(ctx, args, 0)?;
+    let registry = snapshot();
+    let mods = MODS.lock();
+    to_js(
+        ctx,
+        &summarize(&registry.items, registry.recipes.recipes(), &mods, &id),
+    )
+}
+
+/// `ultralightui.listTags()`
+fn js_list_tags<'c>(ctx: &'c JSContext, _args: &[JSValue<'c>]) -> Result<JSValue<'c>, JSValue<'c>> {
+    let registry = snapshot();
+    to_js(
+        ctx,
+        &registry
+            .tags
+            .iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// `ultralightui.getTagItems(tag)`，返回标签展开后的物品ID
+fn js_get_tag_items<'c>(
+    ctx: &'c JSContext,
+    args: &[JSValue<'c>],
+) -> Result<JSValue<'c>, JSValue<'c>> {
+    let tag = arg_string(ctx, args, 0)?;
+    let tag = parse_tag(&tag).map_err(|e| JSValue::new_string(ctx, &e))?;
+    to_js(ctx, &snapshot().tags.resolve(&tag))
+}
+
+#[derive(Serialize)]
+struct RecipeInfo<'a> {
+    #[serde(flatten)]
+    recipe: &'a Recipe,
+    /// 标签原料展开后的物品
+    tag_items: BTreeMap<&'a ResourceLocation, BTreeSet<u32>>,
+}
+
+/// `ultralightui.getRecipe(name)`，配方不存在时返回 `null`
+fn js_get_recipe<'c>(ctx: &'c JSContext, args: &[JSValue<'c>]) -> Result<JSValue<'c>, JSValue<'c>> {
+    let name = arg_string(ctx, args, 0)?;
+    let name = ResourceLocation::parse(&name).map_err(|e| JSValue::new_string(ctx, &e))?;
+    let registry = snapshot();
+    let Some(recipe) = registry.recipes.recipes().get(&name) else {
+        return Ok(JSValue::new_null(ctx));
+    };
+    let tag_items = recipe
+        .material_tags
+        .iter()
+        .map(|(tag, _)| (tag, registry.tags.resolve(tag)))
+        .collect();
+    to_js(ctx, &RecipeInfo { recipe, tag_items })
+}
+
+/// `ultralightui.resolveCrafting(request)`，`request` 为 `ResolveRequest` 对象，失败时抛出异常
+fn js_resolve_crafting<'c>(
+    ctx: &'c JSContext,
+    args: &[JSValue<'c>],
+) -> Result<JSValue<'c>, JSValue<'c>> {
+    let request: ResolveRequest = arg_json(ctx, args, 0)?;
+    let registry = snapshot();
+    let plan = resolve(
+        &registry.items,
+        registry.recipes.recipes(),
+        &registry.tags,
+        &request,
+    )
+    .map_err(|e| JSValue::new_string(ctx, &e))?;
+    to_js(ctx, &plan)
+}
+
+/// `ultralightui.getRecipeSources(id, offset = 0, limit = 50)`
+fn js_get_recipe_sources<'c>(
+    ctx: &'c JSContext,
+    args: &[JSValue<'c>],
+) -> Result<JSValue<'c>, JSValue<'c>> {
+    let id = arg_number(ctx, args, 0, -1.0)?;
+    let offset = arg_number(ctx, args, 1, 0.0)?;
+    let limit = arg_number(ctx, args, 2, 50.0)?;
+    let registry = snapshot();
+    to_js(
+        ctx,
+        &registry
+            .recipes
+            .sources(id as u32, offset as usize, limit as usize),
+    )
+}
+
+/// `ultralightui.getRecipeUsages(id, offset = 0, limit = 50)`
+fn js_get_recipe_usages<'c>(
+    ctx: &'c JSContext,
+    args: &[JSValue<'c>],
+) -> Result<JSValue<'c>, JSValue<'c>> {
+    let id = arg_number(ctx, args, 0, -1.0)?;
+    let offset = arg_number(ctx, args, 1, 0.0)?;
+    let limit = arg_number(ctx, args, 2, 50.0)?;
+    let registry = snapshot();
+    to_js(
+        ctx,
+        &registry
+            .recipes
+            .usages(id as u32, &registry.tags, offset as usize, limit as usize),
+    )
+}
+
+/// `ultralightui.validateRegistry()`
+fn js_validate_registry<'c>(
+    ctx: &'c JSContext,
+    _args: &[JSValue<'c>],
+) -> Result<JSValue<'c>, JSValue<'c>> {
+    let registry = snapshot();
+    to_js(
+        ctx,
+        &validate(&registry.items, registry.recipes.recipes(), &registry.tags),
+    )
+}
+
+/// `ultralightui.getInventory()`
+fn js_get_inventory<'c>(
+    ctx: &'c JSContext,
+    _args: &[JSValue<'c>],
+) -> Result<JSValue<'c>, JSValue<'c>> {
+    let inventory = INVENTORY.lock().clone();
+    to_js(ctx, &inventory)
+}
+
+/// `ultralightui.listCraftable()`
+fn js_list_craftable<'c>(
+    ctx: &'c JSContext,
+    _args: &[JSValue<'c>],
+) -> Result<JSValue<'c>, JSValue<'c>> {
+    let registry = snapshot();
+    let inventory = INVENTORY.lock();
+    to_js(
+        ctx,
+        &craftable(registry.recipes.recipes(), &registry.tags, &inventory),
+    )
+}
+
+/// `ultralightui.findMissing(request)`，`request` 为 `ResolveRequest` 对象，失败时抛出异常
+fn js_find_missing<'c>(
+    ctx: &'c JSContext,
+    args: &[JSValue<'c>],
+) -> Result<JSValue<'c>, JSValue<'c>> {
+    let request: ResolveRequest = arg_json(ctx, args, 0)?;
+    let registry = snapshot();
+    let inventory = INVENTORY.lock();
+    let report = missing_for(
+        &registry.items,
+        registry.recipes.recipes(),
+        &registry.tags,
+        &inventory,
+        &request,
+    )
+    .map_err(|e| JSValue::new_string(ctx, &e))?;
+    to_js(ctx, &report)
+}
+
+/// `ultralightui.formatAmount(id, amount)`，按物品类型、当前语言和能量单位格式化数量
+fn js_format_amount<'c>(
+    ctx: &'c JSContext,
+    args: &[JSValue<'c>],
+) -> Result<JSValue<'c>, JSValue<'c>> {
+    let id = arg_number(ctx, args, 0, -1.0)?;
+    let amount = arg_number(ctx, args, 1, 0.0)?;
+    if id < 0.0 || amount < 0.0 {
+        return Err(JSValue::new_string(
+            ctx,
+            "Item id and amount must be non-negative",
+        ));
+    }
+    match format_item_amount(id as u32, amount as u64) {
+        Ok(text) => Ok(JSValue::new_string(ctx, &text)),
+        Err(e) => Err(JSValue::new_string(ctx, &e)),
+    }
 }
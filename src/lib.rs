@@ -1,6 +1,7 @@
 #[cfg(feature = "surfman")]
 surfman::declare_surfman!();
 
+use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::atomic::Ordering;
 use std::sync::{Arc, OnceLock};
@@ -11,12 +12,29 @@ use ul_next::{Config, Library, app::App, window::WindowFlags};
 use crate::render::renderer_main_wrapper;
 use crate::render::{EXIT_RENDERER, RENDER_MUTEX, RENDER_RECV_STAT_COND, RENDER_SEND_TASK_COND};
 
+mod batch;
+mod datapack;
+mod exchange;
 mod file;
+mod format;
 mod gpu;
 mod helper;
+mod idmap;
+mod inventory;
 mod items;
 mod js;
+mod locale;
+mod mods;
+mod planner;
+mod query;
+mod recipes;
+mod registry;
 mod render;
+mod resolver;
+mod resource;
+mod search;
+mod tags;
+mod validate;
 mod view;
 
 static LIB: OnceLock<Arc<Library>> = OnceLock::new();
@@ -32,6 +50,103 @@ fn read_c_string(ptr: *const u8) -> &'static str {
     }
 }
 
+/// 复制字符串到 `ultralightui_alloc` 分配的内存中，调用方使用 `ultralightui_free` 释放
+fn write_c_string(s: &str) -> usize {
+    let ptr = ultralightui_alloc(s.len() + 1) as *mut u8;
+    assert!(!ptr.is_null());
+    unsafe {
+        std::ptr::copy_nonoverlapping(s.as_ptr(), ptr, s.len());
+        *ptr.add(s.len()) = 0;
+    }
+    ptr as usize
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// 将结果转换为 FFI 状态码 (成功为 1，失败为 0)，错误信息可通过 `ultralightui_last_error` 获取
+fn ffi_status(result: Result<(), String>) -> u32 {
+    match result {
+        Ok(()) => 1,
+        Err(e) => {
+            LAST_ERROR.with_borrow_mut(|last| *last = Some(e));
+            0
+        }
+    }
+}
+
+/// 将结果序列化为 JSON 字符串并返回其指针 (由 `ultralightui_free` 释放)，失败时返回 0
+fn ffi_json<T: serde::Serialize>(result: Result<T, String>) -> usize {
+    let result = result.and_then(|value| {
+        serde_json::to_string(&value).map_err(|e| format!("Failed to serialize result: {}", e))
+    });
+    match result {
+        Ok(json) => write_c_string(&json),
+        Err(e) => {
+            LAST_ERROR.with_borrow_mut(|last| *last = Some(e));
+            0
+        }
+    }
+}
+
+/// 返回字符串结果的指针 (由 `ultralightui_free` 释放)，失败时返回 0
+fn ffi_string(result: Result<&str, String>) -> usize {
+    match result {
+        Ok(s) => write_c_string(s),
+        Err(e) => {
+            LAST_ERROR.with_borrow_mut(|last| *last = Some(e));
+            0
+        }
+    }
+}
+
+/// 复制字节结果到 `ultralightui_alloc` 分配的内存中并返回其指针 (由 `ultralightui_free` 释放)
+/// - 长度写入 `len`，失败时返回 0
+fn ffi_bytes(result: Result<Vec<u8>, String>, len: *mut usize) -> usize {
+    // 先检查参数再分配，失败时不会泄漏内存，也不会在 FFI 边界 panic
+    let result = result.and_then(|data| match len.is_null() {
+        true => Err("Length pointer is null".to_string()),
+        false => Ok(data),
+    });
+    match result {
+        Ok(data) => {
+            let ptr = ultralightui_alloc(data.len().max(1)) as *mut u8;
+            if ptr.is_null() {
+                LAST_ERROR.with_borrow_mut(|last| {
+                    *last = Some(format!("Failed to allocate {} bytes", data.len()))
+                });
+                return 0;
+            }
+            unsafe {
+                std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+                *len = data.len();
+            }
+            ptr as usize
+        }
+        Err(e) => {
+            LAST_ERROR.with_borrow_mut(|last| *last = Some(e));
+            0
+        }
+    }
+}
+
+fn read_bytes(ptr: *const u8, len: usize) -> &'static [u8] {
+    unsafe {
+        assert!(!ptr.is_null());
+        std::slice::from_raw_parts(ptr, len)
+    }
+}
+
+/// 取出当前线程最近一次 FFI 调用的错误信息，没有错误时返回 0
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_last_error() -> usize {
+    match LAST_ERROR.with_borrow_mut(Option::take) {
+        Some(e) => write_c_string(&e),
+        None => 0,
+    }
+}
+
 struct ArboardClipboard {
     clipboard: arboard::Clipboard,
 }
@@ -125,8 +240,11 @@ extern "C" fn ultralightui_client_init() {
         .unwrap();
 }
 
+/// 服务端初始化，之后的增量从空注册表开始计算
 #[unsafe(no_mangle)]
-extern "C" fn ultralightui_server_init() {}
+extern "C" fn ultralightui_server_init() {
+    exchange::reset_baseline();
+}
 
 #[unsafe(no_mangle)]
 extern "C" fn ultralightui_into_render_main() {
@@ -0,0 +1,218 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::file::visit_files;
+use crate::js::dispatch_event;
+use crate::registry::{modify_current, snapshot};
+use crate::{ffi_json, ffi_status, ffi_string, read_c_string, write_c_string};
+
+/// 没有指定回退链时使用的回退语言
+const DEFAULT_FALLBACK: &str = "en_us";
+
+/// 多语言翻译表，对应 MC 的 `assets/<ns>/lang/<locale>.json`
+#[derive(Clone)]
+pub struct Locales {
+    /// 语言 -> (翻译键 -> 文本)
+    langs: BTreeMap<String, BTreeMap<String, String>>,
+    active: Option<String>,
+    fallback: Option<Vec<String>>,
+}
+
+/// 一次加载的统计结果
+#[derive(Serialize, Default)]
+pub struct LangLoadSummary {
+    /// 每种语言加载的翻译条数
+    pub entries: BTreeMap<String, usize>,
+    /// 无法解析的文件及原因
+    pub errors: Vec<String>,
+}
+
+/// 从 `.../assets/<ns>/lang/<locale>.json` (或旧版 `.lang`) 路径中取出语言代码
+fn lang_file_locale(path: &str) -> Option<(String, bool)> {
+    let parts: Vec<&str> = path.split('/').collect();
+    let [.., assets, _ns, lang, file] = parts.as_slice() else {
+        return None;
+    };
+    if *assets != "assets" || *lang != "lang" {
+        return None;
+    }
+    if let Some(locale) = file.strip_suffix(".json") {
+        Some((locale.to_lowercase(), true))
+    } else {
+        file.strip_suffix(".lang")
+            .map(|locale| (locale.to_lowercase(), false))
+    }
+}
+
+fn parse_lang(data: &[u8], json: bool) -> Result<Vec<(String, String)>, String> {
+    let text = std::str::from_utf8(data).map_err(|e| e.to_string())?;
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    if json {
+        let map: BTreeMap<String, serde_json::Value> =
+            serde_json::from_str(text).map_err(|e| e.to_string())?;
+        Ok(map
+            .into_iter()
+            .filter_map(|(k, v)| Some((k, v.as_str()?.to_string())))
+            .collect())
+    } else {
+        Ok(text
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(k, v)| (k.trim().to_string(), v.to_string()))
+            .collect())
+    }
+}
+
+impl Locales {
+    pub const fn new() -> Self {
+        Self {
+            langs: BTreeMap::new(),
+            active: None,
+            fallback: None,
+        }
+    }
+
+    /// 从资源目录或 zip/jar 中加载所有语言文件，后加载的条目覆盖先加载的
+    pub fn load(&mut self, path: &Path) -> Result<LangLoadSummary, String> {
+        let mut summary = LangLoadSummary::default();
+        visit_files(
+            path,
+            |name| lang_file_locale(name).is_some(),
+            |name, data| {
+                let (locale, json) = lang_file_locale(name).unwrap();
+                match parse_lang(data, json) {
+                    Ok(entries) => {
+                        *summary.entries.entry(locale.clone()).or_default() += entries.len();
+                        self.langs.entry(locale).or_default().extend(entries);
+                    }
+                    Err(e) => summary.errors.push(format!("{}: {}", name, e)),
+                }
+            },
+        )?;
+        Ok(summary)
+    }
+
+    pub fn set_active(&mut self, locale: &str) {
+        self.active = Some(locale.to_lowercase());
+    }
+
+    pub fn set_fallback(&mut self, chain: Vec<String>) {
+        self.fallback = Some(chain.into_iter().map(|l| l.to_lowercase()).collect());
+    }
+
+    /// 当前语言，未设置时为回退语言
+    pub fn active(&self) -> &str {
+        self.active.as_deref().unwrap_or(DEFAULT_FALLBACK)
+    }
+
+    /// 翻译查找顺序：当前语言，然后依次是回退链中的语言
+    pub fn chain(&self) -> Vec<&str> {
+        let mut chain = vec![self.active()];
+        let fallback = match &self.fallback {
+            Some(fallback) => fallback.iter().map(String::as_str).collect(),
+            None => vec![DEFAULT_FALLBACK],
+        };
+        for locale in fallback {
+            if !chain.contains(&locale) {
+                chain.push(locale);
+            }
+        }
+        chain
+    }
+
+    pub fn translate(&self, key: &str) -> Option<&str> {
+        self.chain()
+            .into_iter()
+            .find_map(|locale| self.langs.get(locale)?.get(key))
+            .map(String::as_str)
+    }
+
+    pub fn list(&self) -> Vec<&str> {
+        self.langs.keys().map(String::as_str).collect()
+    }
+}
+
+#[derive(Serialize)]
+pub struct LocaleInfo<'a> {
+    pub active: &'a str,
+    pub chain: Vec<&'a str>,
+    pub available: Vec<&'a str>,
+}
+
+impl<'a> LocaleInfo<'a> {
+    pub fn new(locales: &'a Locales) -> Self {
+        Self {
+            active: locales.active(),
+            chain: locales.chain(),
+            available: locales.list(),
+        }
+    }
+}
+
+/// 通知所有页面语言已变化 (`ultralightui:localechange` 事件，`detail` 为 `LocaleInfo`)
+fn notify_locale_change() {
+    let detail = serde_json::to_value(LocaleInfo::new(&snapshot().items.locales));
+    dispatch_event("ultralightui:localechange", &detail.unwrap_or_default());
+}
+
+/// 加载资源目录或 zip/jar 中的语言文件，返回 `LangLoadSummary` 的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_load_lang(path: *const u8) -> usize {
+    let path = Path::new(read_c_string(path));
+    let result = modify_current(|reg| reg.items.load_lang(path));
+    if result.is_ok() {
+        notify_locale_change();
+    }
+    ffi_json(result)
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_set_locale(locale: *const u8) {
+    let locale = read_c_string(locale);
+    modify_current(|reg| reg.items.set_locale(locale));
+    notify_locale_change();
+}
+
+/// 设置回退链，`chain` 为语言代码数组的 JSON，例如 `["en_us"]`
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_set_locale_fallback(chain: *const u8) -> u32 {
+    let chain = read_c_string(chain);
+    ffi_status(
+        serde_json::from_str::<Vec<String>>(chain)
+            .map_err(|e| format!("Invalid locale chain: {}", e))
+            .map(|chain| {
+                modify_current(|reg| reg.items.set_locale_fallback(chain));
+                notify_locale_change();
+            }),
+    )
+}
+
+/// 返回 `LocaleInfo` 的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_get_locale() -> usize {
+    let registry = snapshot();
+    ffi_json(Ok(LocaleInfo::new(&registry.items.locales)))
+}
+
+/// 按当前语言翻译一个键，找不到时返回键本身
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_translate(key: *const u8) -> usize {
+    let key = read_c_string(key);
+    let registry = snapshot();
+    write_c_string(registry.items.locales.translate(key).unwrap_or(key))
+}
+
+/// 按当前语言返回物品名称，物品不存在时返回 0
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_localize_item(id: u32) -> usize {
+    let registry = snapshot();
+    ffi_string(
+        registry
+            .items
+            .get_by_id(id)
+            .map(|item| registry.items.l10n(item))
+            .ok_or_else(|| format!("Unknown item id {}", id)),
+    )
+}
@@ -0,0 +1,133 @@
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::items::{ItemManager, Recipe, recipes_in_namespace};
+use crate::registry::snapshot;
+use crate::resource::{ResourceLocation, is_valid_namespace};
+use crate::{ffi_json, ffi_status, read_c_string};
+
+#[derive(Clone, Serialize)]
+pub struct ModInfo {
+    /// 模组ID，同时也是物品和配方注册名的命名空间
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub description: String,
+}
+
+/// 已注册的模组，按ID排序
+pub static MODS: Mutex<BTreeMap<String, ModInfo>> = Mutex::new(BTreeMap::new());
+
+pub fn register_mod(info: ModInfo) -> Result<(), String> {
+    if !is_valid_namespace(&info.id) {
+        return Err(format!("Invalid mod id '{}'", info.id));
+    }
+    let mut mods = MODS.lock();
+    if mods.contains_key(&info.id) {
+        return Err(format!("Mod with id '{}' already exists!", info.id));
+    }
+    mods.insert(info.id.clone(), info);
+    Ok(())
+}
+
+/// 模组信息及其注册的物品和配方数量
+#[derive(Serialize)]
+pub struct ModSummary {
+    #[serde(flatten)]
+    pub info: ModInfo,
+    /// 是否通过 `ultralightui_register_mod` 注册，未注册的命名空间只有ID
+    pub registered: bool,
+    pub items: usize,
+    pub recipes: usize,
+}
+
+/// 列出所有模组，包括注册过物品或配方但没有注册模组信息的命名空间
+pub fn list_mods(
+    items: &ItemManager,
+    recipes: &BTreeMap<ResourceLocation, Recipe>,
+    mods: &BTreeMap<String, ModInfo>,
+) -> Vec<ModSummary> {
+    let mut ids: Vec<&str> = mods.keys().map(String::as_str).collect();
+    ids.extend(items.namespaces.keys().map(String::as_str));
+    ids.extend(recipes.keys().map(ResourceLocation::namespace));
+    ids.sort_unstable();
+    ids.dedup();
+    ids.into_iter()
+        .filter_map(|id| summarize(items, recipes, mods, id))
+        .collect()
+}
+
+/// 模组的摘要，既没有注册模组信息也没有注册物品或配方时返回 `None`
+pub fn summarize(
+    items: &ItemManager,
+    recipes: &BTreeMap<ResourceLocation, Recipe>,
+    mods: &BTreeMap<String, ModInfo>,
+    id: &str,
+) -> Option<ModSummary> {
+    let item_count = items.ids_by_namespace(id).len();
+    let recipe_count = recipes_in_namespace(recipes, id).count();
+    let (info, registered) = match mods.get(id) {
+        Some(info) => (info.clone(), true),
+        None if item_count == 0 && recipe_count == 0 => return None,
+        None => (
+            ModInfo {
+                id: id.to_string(),
+                name: id.to_string(),
+                version: String::new(),
+                description: String::new(),
+            },
+            false,
+        ),
+    };
+    Some(ModSummary {
+        info,
+        registered,
+        items: item_count,
+        recipes: recipe_count,
+    })
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_register_mod(
+    id: *const u8,
+    name: *const u8,
+    version: *const u8,
+    description: *const u8,
+) -> u32 {
+    let id = read_c_string(id).to_string();
+    let name = read_c_string(name).to_string();
+    let version = read_c_string(version).to_string();
+    let description = read_c_string(description).to_string();
+
+    ffi_status(register_mod(ModInfo {
+        id,
+        name,
+        version,
+        description,
+    }))
+}
+
+/// 返回 `ModSummary` 数组的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_list_mods() -> usize {
+    let registry = snapshot();
+    let mods = MODS.lock();
+    ffi_json(Ok(list_mods(
+        &registry.items,
+        registry.recipes.recipes(),
+        &mods,
+    )))
+}
+
+/// 返回单个模组 `ModSummary` 的 JSON，模组不存在时返回 0
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_get_mod(id: *const u8) -> usize {
+    let id = read_c_string(id);
+    let registry = snapshot();
+    let mods = MODS.lock();
+    ffi_json(
+        summarize(&registry.items, registry.recipes.recipes(), &mods, id)
+            .ok_or_else(|| format!("Unknown mod id '{}'", id)),
+    )
+}
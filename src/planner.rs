@@ -0,0 +1,343 @@
+use microlp::{ComparisonOp, OptimizationDirection, Problem, Variable};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::helper::ItemStacks;
+use crate::items::{ItemManager, Recipe};
+use crate::registry::snapshot;
+use crate::resource::ResourceLocation;
+use crate::tags::TagRegistry;
+use crate::{ffi_json, read_c_string};
+
+/// 数值误差容限，小于它的速率视为 0
+const EPSILON: f64 = 1e-9;
+
+fn default_tps() -> f64 {
+    20.0
+}
+
+fn default_raw_cost() -> f64 {
+    1.0
+}
+
+fn default_machine_cost() -> f64 {
+    1e-3
+}
+
+/// 产线规划请求
+#[derive(Deserialize)]
+pub struct PlanRequest {
+    /// 目标产出速率 (物品ID -> 每秒数量)
+    pub targets: BTreeMap<u32, f64>,
+    /// 视为外部输入、不再用配方生产的物品
+    #[serde(default)]
+    pub raw: BTreeSet<u32>,
+    /// 禁止使用的配方名称
+    #[serde(default)]
+    pub exclude: BTreeSet<ResourceLocation>,
+    /// 指定标签原料使用的物品，未指定时使用标签中ID最小的物品
+    #[serde(default)]
+    pub prefer_tags: BTreeMap<ResourceLocation, u32>,
+    /// 每秒 tick 数
+    #[serde(default = "default_tps")]
+    pub tps: f64,
+    /// 目标函数中每单位原材料输入速率的代价
+    #[serde(default = "default_raw_cost")]
+    pub raw_cost: f64,
+    /// 目标函数中每台机器的代价
+    #[serde(default = "default_machine_cost")]
+    pub machine_cost: f64,
+}
+
+/// 单个配方的规划结果
+#[derive(Serialize)]
+pub struct RecipePlan {
+    pub name: ResourceLocation,
+    /// 所需机器数量
+    pub machines: f64,
+    /// 向上取整后的机器数量
+    pub machines_ceil: u64,
+    /// 所有机器合计每秒执行配方的次数
+    pub crafts_per_second: f64,
+}
+
+/// 单个物品的流量 (每秒数量)
+#[derive(Serialize, Default)]
+pub struct ItemFlow {
+    pub produced: f64,
+    pub consumed: f64,
+}
+
+/// 产线规划结果
+#[derive(Serialize)]
+pub struct ProductionPlan {
+    pub recipes: Vec<RecipePlan>,
+    /// 各物品的生产和消耗速率
+    pub flows: BTreeMap<u32, ItemFlow>,
+    /// 外部输入的原材料速率
+    pub raw: BTreeMap<u32, f64>,
+    /// 超出目标的剩余产出速率 (副产物)
+    pub surplus: BTreeMap<u32, f64>,
+}
+
+/// 每台机器每秒执行配方的次数，耗时为 0 的配方按 1 tick 计算
+fn crafts_per_machine(recipe: &Recipe, tps: f64) -> f64 {
+    tps / recipe.timecost.max(1) as f64
+}
+
+/// 配方的实际输入，标签原料替换为标签中的代表物品
+fn recipe_materials(
+    name: &ResourceLocation,
+    recipe: &Recipe,
+    tags: &TagRegistry,
+    request: &PlanRequest,
+) -> Result<ItemStacks, String> {
+    let mut materials: ItemStacks = recipe
+        .material
+        .iter()
+        .filter(|&(id, _)| recipe.is_consumed(id))
+        .collect();
+    for (tag, &count) in recipe.material_tags.iter() {
+        let prefer = request.prefer_tags.get(tag).copied();
+        let id = tags
+            .representative(tag, prefer)
+            .ok_or_else(|| format!("Tag '#{}' used by recipe '{}' has no items", tag, name))?;
+        materials.add(id, count);
+    }
+    Ok(materials)
+}
+
+struct UsedRecipe<'a> {
+    name: &'a ResourceLocation,
+    recipe: &'a Recipe,
+    /// 实际输入
+    materials: ItemStacks,
+}
+
+/// 从目标物品反向收集所有可能用到的配方
+fn collect_recipes<'a>(
+    request: &PlanRequest,
+    recipes: &'a BTreeMap<ResourceLocation, Recipe>,
+    tags: &TagRegistry,
+) -> Result<Vec<UsedRecipe<'a>>, String> {
+    let mut producers: BTreeMap<u32, Vec<(&ResourceLocation, &Recipe)>> = BTreeMap::new();
+    for (name, recipe) in recipes {
+        if request.exclude.contains(name) {
+            continue;
+        }
+        for id in recipe.products.ids() {
+            if recipe.expected_output(id) > 0.0 {
+                producers.entry(id).or_default().push((name, recipe));
+            }
+        }
+    }
+
+    let mut used = BTreeMap::new();
+    let mut visited = BTreeSet::new();
+    let mut queue: Vec<u32> = request.targets.keys().copied().collect();
+    while let Some(item) = queue.pop() {
+        if !visited.insert(item) || request.raw.contains(&item) {
+            continue;
+        }
+        for &(name, recipe) in producers.get(&item).into_iter().flatten() {
+            if !used.contains_key(name) {
+                let materials = recipe_materials(name, recipe, tags, request)?;
+                queue.extend(materials.ids());
+                used.insert(name, (recipe, materials));
+            }
+        }
+    }
+    Ok(used
+        .into_iter()
+        .map(|(name, (recipe, materials))| UsedRecipe {
+            name,
+            recipe,
+            materials,
+        })
+        .collect())
+}
+
+/// 以线性规划求解满足目标速率的产线
+/// - 每个配方的机器数量是一个非负变量，每个物品的净产出加外部输入必须不少于目标速率
+/// - 原材料 (被指定或没有可用配方的物品) 可以从外部输入，目标是最小化原材料和机器的总代价
+/// - 存在多个可用配方时由求解器在其中选择或组合
+/// - 标签原料按标签中的代表物品计算
+/// - 按概率产出的产物以期望产量计算，不消耗的原料不计入物料流
+pub fn plan(
+    items: &ItemManager,
+    recipes: &BTreeMap<ResourceLocation, Recipe>,
+    tags: &TagRegistry,
+    request: &PlanRequest,
+) -> Result<ProductionPlan, String> {
+    if request.tps <= 0.0 {
+        return Err(format!("Invalid ticks per second {}", request.tps));
+    }
+    for (&id, &rate) in &request.targets {
+        if items.get_by_id(id).is_none() {
+            return Err(format!("Unknown item id {}", id));
+        }
+        if !(rate.is_finite() && rate >= 0.0) {
+            return Err(format!("Invalid target rate {} for item {}", rate, id));
+        }
+    }
+
+    let used = collect_recipes(request, recipes, tags)?;
+
+    let mut problem = Problem::new(OptimizationDirection::Minimize);
+    let machines: Vec<Variable> = used
+        .iter()
+        .map(|_| problem.add_var(request.machine_cost, (0.0, f64::INFINITY)))
+        .collect();
+
+    // 每个物品的净产出表达式，按配方合并系数 (同一配方可能既消耗又产出同一物品，
+    // 求解器不接受同一变量在一个约束中出现两次)
+    let mut terms: BTreeMap<u32, BTreeMap<usize, (Variable, f64)>> = BTreeMap::new();
+    for (index, (used, &var)) in used.iter().zip(&machines).enumerate() {
+        let rate = crafts_per_machine(used.recipe, request.tps);
+        let mut add = |id: u32, coeff: f64| {
+            terms
+                .entry(id)
+                .or_default()
+                .entry(index)
+                .or_insert((var, 0.0))
+                .1 += coeff;
+        };
+        for id in used.recipe.products.ids() {
+            add(id, used.recipe.expected_output(id) * rate);
+        }
+        for (id, count) in used.materials.iter() {
+            add(id, -(count as f64) * rate);
+        }
+    }
+    for &id in request.targets.keys() {
+        terms.entry(id).or_default();
+    }
+
+    let produced: BTreeSet<u32> = used
+        .iter()
+        .flat_map(|used| used.recipe.products.ids())
+        .collect();
+
+    let mut supplies = BTreeMap::new();
+    for (&id, recipe_terms) in &terms {
+        let mut expr: Vec<(Variable, f64)> = recipe_terms.values().copied().collect();
+        if request.raw.contains(&id) || !produced.contains(&id) {
+            let var = problem.add_var(request.raw_cost, (0.0, f64::INFINITY));
+            supplies.insert(id, var);
+            expr.push((var, 1.0));
+        }
+        let target = request.targets.get(&id).copied().unwrap_or(0.0);
+        problem.add_constraint(expr.as_slice(), ComparisonOp::Ge, target);
+    }
+
+    let solution = problem
+        .solve()
+        .map_err(|e| format!("Failed to solve production plan: {}", e))?;
+    let value = |var: Variable| {
+        let v = *solution.var_value(var);
+        if v.abs() < EPSILON { 0.0 } else { v }
+    };
+
+    let mut plan = ProductionPlan {
+        recipes: Vec::new(),
+        flows: BTreeMap::new(),
+        raw: BTreeMap::new(),
+        surplus: BTreeMap::new(),
+    };
+
+    for (used, &var) in used.iter().zip(&machines) {
+        let count = value(var);
+        if count == 0.0 {
+            continue;
+        }
+        let crafts = count * crafts_per_machine(used.recipe, request.tps);
+        for id in used.recipe.products.ids() {
+            plan.flows.entry(id).or_default().produced += used.recipe.expected_output(id) * crafts;
+        }
+        for (id, amount) in used.materials.iter() {
+            plan.flows.entry(id).or_default().consumed += amount as f64 * crafts;
+        }
+        plan.recipes.push(RecipePlan {
+            name: used.name.clone(),
+            machines: count,
+            machines_ceil: (count - EPSILON).ceil() as u64,
+            crafts_per_second: crafts,
+        });
+    }
+
+    for (&id, &var) in &supplies {
+        let rate = value(var);
+        if rate > 0.0 {
+            plan.raw.insert(id, rate);
+        }
+    }
+
+    for (&id, flow) in &plan.flows {
+        let supplied = plan.raw.get(&id).copied().unwrap_or(0.0);
+        let target = request.targets.get(&id).copied().unwrap_or(0.0);
+        let surplus = flow.produced + supplied - flow.consumed - target;
+        if surplus > EPSILON {
+            plan.surplus.insert(id, surplus);
+        }
+    }
+
+    Ok(plan)
+}
+
+/// 规划产线，`request` 为 `PlanRequest` 的 JSON，返回 `ProductionPlan` 的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_plan_production(request: *const u8) -> usize {
+    let request = read_c_string(request);
+    ffi_json(
+        serde_json::from_str::<PlanRequest>(request)
+            .map_err(|e| format!("Invalid plan request: {}", e))
+            .and_then(|request| {
+                let registry = snapshot();
+                plan(
+                    &registry.items,
+                    registry.recipes.recipes(),
+                    &registry.tags,
+                    &request,
+                )
+            }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::{Item, ItemType};
+
+    fn item(name: &str) -> Item {
+        Item {
+            id: 0,
+            ty: ItemType::Solid,
+            name: ResourceLocation::parse(name).unwrap(),
+            i18n: name.to_string(),
+            l10n: String::new(),
+            max_stack: 64,
+            description: String::new(),
+        }
+    }
+
+    #[test]
+    fn recipe_consuming_and_producing_the_same_item() {
+        let mut items = ItemManager::new();
+        let seed = items.insert(item("seed")).unwrap();
+        let wheat = items.insert(item("wheat")).unwrap();
+        let mut recipe = Recipe::new();
+        recipe.timecost = 20;
+        recipe.add_material(seed, 1).unwrap();
+        recipe.add_product(seed, 2).unwrap();
+        recipe.add_product(wheat, 1).unwrap();
+        let mut recipes = BTreeMap::new();
+        recipes.insert(ResourceLocation::parse("farm").unwrap(), recipe);
+        let request: PlanRequest =
+            serde_json::from_str(&format!(r#"{{"targets": {{"{}": 2.0}}}}"#, wheat)).unwrap();
+
+        let plan = plan(&items, &recipes, &TagRegistry::new(), &request).unwrap();
+        assert_eq!(plan.recipes.len(), 1);
+        assert!((plan.recipes[0].machines - 2.0).abs() < 1e-6);
+        assert!(!plan.raw.contains_key(&seed));
+    }
+}
@@ -0,0 +1,377 @@
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::items::{Item, ItemManager, ItemType};
+use crate::registry::snapshot;
+use crate::search::SearchResult;
+use crate::tags::TagRegistry;
+use crate::{ffi_json, read_c_string};
+
+/// 查询语法树
+/// - `word` 普通搜索词，`@mod` 模组，`#tag` 标签，`$text` 描述，`^type` 物品类型
+/// - `-x` 取反，`a b` 同时满足，`a | b` 满足其一，`( ... )` 分组
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Query {
+    Term {
+        text: String,
+    },
+    Mod {
+        name: String,
+    },
+    Tag {
+        name: String,
+        /// 名称包含 `name` 的所有标签中的物品，由 `bind_tags` 填充
+        #[serde(skip)]
+        items: BTreeSet<u32>,
+    },
+    Description {
+        text: String,
+    },
+    Type {
+        ty: ItemType,
+    },
+    Not {
+        query: Box<Query>,
+    },
+    And {
+        queries: Vec<Query>,
+    },
+    Or {
+        queries: Vec<Query>,
+    },
+}
+
+/// 查询解析错误，`position` 为出错位置的字符下标
+#[derive(Debug, Serialize)]
+pub struct QueryError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Prefixed(char, String),
+    Minus,
+    Or,
+    LParen,
+    RParen,
+}
+
+fn is_special(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '|' | '(' | ')' | '"')
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    // 读取一个普通词或带引号的字符串
+    let read_word = |i: &mut usize| -> Result<String, QueryError> {
+        if chars.get(*i) == Some(&'"') {
+            let start = *i;
+            *i += 1;
+            let mut text = String::new();
+            loop {
+                match chars.get(*i) {
+                    None => {
+                        return Err(QueryError {
+                            position: start,
+                            message: "Unterminated quoted string".to_string(),
+                        });
+                    }
+                    Some('"') => {
+                        *i += 1;
+                        return Ok(text);
+                    }
+                    Some(&c) => {
+                        text.push(c);
+                        *i += 1;
+                    }
+                }
+            }
+        }
+        let mut text = String::new();
+        while let Some(&c) = chars.get(*i) {
+            if is_special(c) {
+                break;
+            }
+            text.push(c);
+            *i += 1;
+        }
+        Ok(text)
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let start = i;
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '|' => {
+                tokens.push((start, Token::Or));
+                i += 1;
+            }
+            '(' => {
+                tokens.push((start, Token::LParen));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((start, Token::RParen));
+                i += 1;
+            }
+            '-' => {
+                tokens.push((start, Token::Minus));
+                i += 1;
+            }
+            '@' | '#' | '$' | '^' => {
+                i += 1;
+                let text = read_word(&mut i)?;
+                if text.is_empty() {
+                    return Err(QueryError {
+                        position: start,
+                        message: format!("Expected text after '{}'", c),
+                    });
+                }
+                tokens.push((start, Token::Prefixed(c, text)));
+            }
+            _ => {
+                let text = read_word(&mut i)?;
+                tokens.push((start, Token::Word(text)));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_type(text: &str, position: usize) -> Result<ItemType, QueryError> {
+    let text = text.to_lowercase();
+    let types = [
+        ("solid", ItemType::Solid),
+        ("fluid", ItemType::Fluid),
+        ("gas", ItemType::Gas),
+        ("energy", ItemType::Energy),
+        ("placeholder", ItemType::Placeholder),
+    ];
+    types
+        .into_iter()
+        .find(|(name, _)| name.starts_with(&text))
+        .map(|(_, ty)| ty)
+        .ok_or_else(|| QueryError {
+            position,
+            message: format!("Unknown item type '{}'", text),
+        })
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |&(p, _)| p)
+    }
+
+    fn parse_or(&mut self) -> Result<Query, QueryError> {
+        let mut queries = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            queries.push(self.parse_and()?);
+        }
+        Ok(if queries.len() == 1 {
+            queries.pop().unwrap()
+        } else {
+            Query::Or { queries }
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Query, QueryError> {
+        let mut queries = Vec::new();
+        while !matches!(self.peek(), None | Some(Token::Or) | Some(Token::RParen)) {
+            queries.push(self.parse_unary()?);
+        }
+        match queries.len() {
+            0 => Err(QueryError {
+                position: self.position(),
+                message: "Expected a search term".to_string(),
+            }),
+            1 => Ok(queries.pop().unwrap()),
+            _ => Ok(Query::And { queries }),
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Query, QueryError> {
+        let position = self.position();
+        let Some((_, token)) = self.tokens.get(self.pos) else {
+            return Err(QueryError {
+                position,
+                message: "Expected a search term".to_string(),
+            });
+        };
+        self.pos += 1;
+        match token {
+            Token::Minus => {
+                if matches!(self.peek(), None | Some(Token::Or) | Some(Token::RParen)) {
+                    return Err(QueryError {
+                        position,
+                        message: "Expected a search term after '-'".to_string(),
+                    });
+                }
+                Ok(Query::Not {
+                    query: Box::new(self.parse_unary()?),
+                })
+            }
+            Token::LParen => {
+                let query = self.parse_or()?;
+                if self.peek() != Some(&Token::RParen) {
+                    return Err(QueryError {
+                        position: self.position(),
+                        message: "Expected ')'".to_string(),
+                    });
+                }
+                self.pos += 1;
+                Ok(query)
+            }
+            Token::RParen => Err(QueryError {
+                position,
+                message: "Unexpected ')'".to_string(),
+            }),
+            Token::Or => Err(QueryError {
+                position,
+                message: "Unexpected '|'".to_string(),
+            }),
+            Token::Word(text) => Ok(Query::Term {
+                text: text.to_lowercase(),
+            }),
+            Token::Prefixed(prefix, text) => Ok(match prefix {
+                '@' => Query::Mod {
+                    name: text.to_lowercase(),
+                },
+                '#' => Query::Tag {
+                    name: text.to_lowercase(),
+                    items: BTreeSet::new(),
+                },
+                '$' => Query::Description {
+                    text: text.to_lowercase(),
+                },
+                _ => Query::Type {
+                    ty: parse_type(text, position)?,
+                },
+            }),
+        }
+    }
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Query, QueryError> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            pos: 0,
+            end: input.chars().count(),
+        };
+        let query = parser.parse_or()?;
+        if parser.pos < parser.tokens.len() {
+            return Err(QueryError {
+                position: parser.position(),
+                message: "Unexpected ')'".to_string(),
+            });
+        }
+        Ok(query)
+    }
+
+    /// 展开查询中的标签条件，`#tag` 匹配名称中包含该文本的任意标签
+    pub fn bind_tags(&mut self, tags: &TagRegistry) {
+        match self {
+            Query::Tag { name, items } => {
+                *items = tags
+                    .iter()
+                    .filter(|(tag, _)| tag.to_string().contains(name.as_str()))
+                    .flat_map(|(tag, _)| tags.resolve(tag))
+                    .collect();
+            }
+            Query::Not { query } => query.bind_tags(tags),
+            Query::And { queries } | Query::Or { queries } => {
+                queries.iter_mut().for_each(|q| q.bind_tags(tags));
+            }
+            _ => {}
+        }
+    }
+
+    /// 对单个物品求值，匹配时返回用于排序的得分
+    pub fn eval(&self, items: &ItemManager, item: &Item) -> Option<u32> {
+        match self {
+            Query::Term { text } => items.search.score(item.id, text),
+            Query::Mod { name } => item.name.namespace().contains(name.as_str()).then_some(0),
+            Query::Tag { items, .. } => items.contains(&item.id).then_some(0),
+            Query::Description { text } => item
+                .description
+                .to_lowercase()
+                .contains(text.as_str())
+                .then_some(0),
+            Query::Type { ty } => (item.ty == *ty).then_some(0),
+            Query::Not { query } => match query.eval(items, item) {
+                Some(_) => None,
+                None => Some(0),
+            },
+            Query::And { queries } => {
+                let mut total = 0;
+                for query in queries {
+                    total += query.eval(items, item)?;
+                }
+                Some(total)
+            }
+            Query::Or { queries } => queries.iter().filter_map(|q| q.eval(items, item)).max(),
+        }
+    }
+}
+
+/// 按查询语句筛选物品，结果按得分从高到低排序
+pub fn query_items(
+    items: &ItemManager,
+    tags: &TagRegistry,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<SearchResult>, QueryError> {
+    let mut query = Query::parse(query)?;
+    query.bind_tags(tags);
+    let mut results: Vec<SearchResult> = items
+        .items
+        .iter()
+        .filter_map(|item| {
+            Some(SearchResult {
+                id: item.id,
+                name: item.name.clone(),
+                l10n: items.l10n(item).to_string(),
+                score: query.eval(items, item)?,
+            })
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.cmp(&a.score).then(a.id.cmp(&b.id)));
+    results.truncate(limit);
+    Ok(results)
+}
+
+/// 按查询语句筛选物品，返回 `SearchResult` 数组的 JSON，语法错误时返回 0
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_query_items(query: *const u8, limit: u32) -> usize {
+    let query = read_c_string(query);
+    let registry = snapshot();
+    ffi_json(
+        query_items(&registry.items, &registry.tags, query, limit as usize)
+            .map_err(|e| e.to_string()),
+    )
+}
@@ -0,0 +1,227 @@
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use crate::items::{Recipe, RecipeInput, RecipeManager};
+use crate::registry::{modify, read};
+use crate::resource::ResourceLocation;
+use crate::tags::parse_tag;
+use crate::{ffi_status, read_c_string};
+
+/// 正在通过 FFI 构建、尚未提交的配方
+static PENDING_RECIPES: Mutex<BTreeMap<u32, Recipe>> = Mutex::new(BTreeMap::new());
+static NEXT_PENDING_ID: AtomicU32 = AtomicU32::new(1);
+
+/// 将配方加入配方表，返回分配的配方ID
+pub fn add_recipe(name: ResourceLocation, recipe: Recipe) -> Result<u32, String> {
+    modify(|reg| insert_recipe(reg.recipes, name, recipe))
+}
+
+/// 将配方加入指定的配方表，用于已经持有注册表的调用者，没有产物的配方不能加入
+pub fn insert_recipe(
+    recipes: &mut RecipeManager,
+    name: ResourceLocation,
+    mut recipe: Recipe,
+) -> Result<u32, String> {
+    if recipe.products.is_empty() {
+        return Err(format!("Recipe '{}' has no products", name));
+    }
+    if recipes.recipes().contains_key(&name) {
+        return Err(format!("Recipe with name '{}' already exists!", name));
+    }
+    recipe.id = recipes.next_id()?;
+    recipe.name = Some(name.clone());
+    let id = recipe.id;
+    recipes.insert(name, recipe);
+    Ok(id)
+}
+
+fn with_pending(
+    handle: u32,
+    f: impl FnOnce(&mut Recipe) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut pending = PENDING_RECIPES.lock();
+    let recipe = pending
+        .get_mut(&handle)
+        .ok_or_else(|| format!("Unknown recipe handle {}", handle))?;
+    f(recipe)
+}
+
+fn resolve_item_id(id: u32) -> Result<u32, String> {
+    read(|reg| reg.items.get_by_id(id).map(|item| item.id))
+        .ok_or_else(|| format!("Unknown item id {}", id))
+}
+
+fn resolve_item_name(name: &str) -> Result<u32, String> {
+    let name = ResourceLocation::parse(name)?;
+    read(|reg| reg.items.id_by_name(&name)).ok_or_else(|| format!("Unknown item name '{}'", name))
+}
+
+/// 开始构建一个新配方，返回用于后续调用的句柄
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_begin() -> u32 {
+    let handle = NEXT_PENDING_ID.fetch_add(1, Ordering::SeqCst);
+    PENDING_RECIPES.lock().insert(handle, Recipe::new());
+    handle
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_add_material(handle: u32, item: u32, count: u64) -> u32 {
+    ffi_status(
+        resolve_item_id(item)
+            .and_then(|id| with_pending(handle, |recipe| recipe.add_material(id, count))),
+    )
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_add_material_by_name(
+    handle: u32,
+    name: *const u8,
+    count: u64,
+) -> u32 {
+    let name = read_c_string(name);
+    ffi_status(
+        resolve_item_name(name)
+            .and_then(|id| with_pending(handle, |recipe| recipe.add_material(id, count))),
+    )
+}
+
+/// 添加一项标签原料，`tag` 可以带 `#` 前缀，标签不需要事先注册
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_add_material_tag(handle: u32, tag: *const u8, count: u64) -> u32 {
+    let tag = read_c_string(tag);
+    ffi_status(
+        parse_tag(tag)
+            .and_then(|tag| with_pending(handle, |recipe| recipe.add_material_tag(tag, count))),
+    )
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_add_product(handle: u32, item: u32, count: u64) -> u32 {
+    ffi_status(
+        resolve_item_id(item)
+            .and_then(|id| with_pending(handle, |recipe| recipe.add_product(id, count))),
+    )
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_add_product_by_name(
+    handle: u32,
+    name: *const u8,
+    count: u64,
+) -> u32 {
+    let name = read_c_string(name);
+    ffi_status(
+        resolve_item_name(name)
+            .and_then(|id| with_pending(handle, |recipe| recipe.add_product(id, count))),
+    )
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_set_timecost(handle: u32, ticks: u64) -> u32 {
+    ffi_status(with_pending(handle, |recipe| {
+        recipe.timecost = ticks;
+        Ok(())
+    }))
+}
+
+/// 设置产物的产出概率 (0, 1]，产物需要先通过 `add_product` 添加
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_set_product_chance(handle: u32, item: u32, chance: f64) -> u32 {
+    ffi_status(with_pending(handle, |recipe| {
+        recipe.set_chance(item, chance)
+    }))
+}
+
+/// 设置原料是否在合成中消耗 (`consumed` 非 0 为消耗)，原料需要先通过 `add_material` 添加
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_set_material_consumed(
+    handle: u32,
+    item: u32,
+    consumed: u32,
+) -> u32 {
+    ffi_status(with_pending(handle, |recipe| {
+        recipe.set_consumed(item, consumed != 0)
+    }))
+}
+
+/// 设置配方类别或机器类型，例如 `minecraft:smelting`
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_set_category(handle: u32, category: *const u8) -> u32 {
+    let category = read_c_string(category);
+    ffi_status(ResourceLocation::parse(category).and_then(|category| {
+        with_pending(handle, |recipe| {
+            recipe.category = Some(category);
+            Ok(())
+        })
+    }))
+}
+
+fn add_catalyst(handle: u32, id: u32) -> Result<(), String> {
+    with_pending(handle, |recipe| {
+        recipe.add_catalyst(id);
+        Ok(())
+    })
+}
+
+/// 添加一个催化剂或工作站物品，该物品不会被消耗
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_add_catalyst(handle: u32, item: u32) -> u32 {
+    ffi_status(resolve_item_id(item).and_then(|id| add_catalyst(handle, id)))
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_add_catalyst_by_name(handle: u32, name: *const u8) -> u32 {
+    let name = read_c_string(name);
+    ffi_status(resolve_item_name(name).and_then(|id| add_catalyst(handle, id)))
+}
+
+/// 设置网格布局的尺寸，所有格子清空
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_set_layout(handle: u32, width: u32, height: u32) -> u32 {
+    ffi_status(with_pending(handle, |recipe| {
+        recipe.set_layout(width, height)
+    }))
+}
+
+fn set_slot(handle: u32, x: u32, y: u32, input: RecipeInput) -> Result<(), String> {
+    with_pending(handle, |recipe| recipe.set_slot(x, y, input))
+}
+
+/// 设置网格中 (`x`, `y`) 格的物品，仅影响布局，原料数量仍由 `add_material` 指定
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_set_slot(handle: u32, x: u32, y: u32, item: u32) -> u32 {
+    ffi_status(resolve_item_id(item).and_then(|id| set_slot(handle, x, y, RecipeInput::Item(id))))
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_set_slot_tag(handle: u32, x: u32, y: u32, tag: *const u8) -> u32 {
+    let tag = read_c_string(tag);
+    ffi_status(parse_tag(tag).and_then(|tag| set_slot(handle, x, y, RecipeInput::Tag(tag))))
+}
+
+/// 以给定名称提交配方，无论成功与否句柄都会失效
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_commit(handle: u32, name: *const u8) -> u32 {
+    let name = read_c_string(name);
+    let Some(recipe) = PENDING_RECIPES.lock().remove(&handle) else {
+        return ffi_status(Err(format!("Unknown recipe handle {}", handle)));
+    };
+    ffi_status(ResourceLocation::parse(name).and_then(|name| add_recipe(name, recipe).map(|_| ())))
+}
+
+/// 放弃一个尚未提交的配方
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_discard(handle: u32) {
+    PENDING_RECIPES.lock().remove(&handle);
+}
+
+/// 放弃所有尚未提交的配方，已有的句柄全部失效
+pub fn discard_pending() {
+    PENDING_RECIPES.lock().clear();
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_recipe_discard_all() {
+    discard_pending();
+}
@@ -0,0 +1,309 @@
+use arc_swap::ArcSwap;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::sync::{Arc, LazyLock};
+
+use crate::items::{ItemManager, RecipeManager};
+use crate::js::dispatch_event;
+use crate::recipes::discard_pending;
+use crate::resource::ResourceLocation;
+use crate::tags::{TagRegistry, parse_tag};
+use crate::validate::{ValidationReport, validate};
+use crate::{ffi_json, ffi_status, read_c_string};
+
+/// 物品表、配方表和标签表的一个不可变版本
+#[derive(Clone)]
+pub struct Registry {
+    pub items: ItemManager,
+    pub recipes: RecipeManager,
+    pub tags: TagRegistry,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self {
+            items: ItemManager::new(),
+            recipes: RecipeManager::new(),
+            tags: TagRegistry::new(),
+        }
+    }
+
+    fn as_mut(&mut self) -> RegistryMut<'_> {
+        RegistryMut {
+            items: &mut self.items,
+            recipes: &mut self.recipes,
+            tags: &mut self.tags,
+        }
+    }
+}
+
+/// 当前发布的注册表，读取方不加锁
+static CURRENT: LazyLock<ArcSwap<Registry>> =
+    LazyLock::new(|| ArcSwap::from_pointee(Registry::new()));
+
+/// 写入方持有的状态，同一时间只有一个写入方
+struct Writer {
+    /// 最新的注册表，与 `CURRENT` 共享
+    current: Arc<Registry>,
+    /// 重载过程中在旁边构建的新注册表
+    staging: Option<Registry>,
+}
+
+static WRITER: LazyLock<Mutex<Writer>> = LazyLock::new(|| {
+    Mutex::new(Writer {
+        current: Arc::new(Registry::new()),
+        staging: None,
+    })
+});
+
+/// 发布写入方的最新注册表，调用方须持有写入锁
+fn publish(writer: &Writer) {
+    CURRENT.store(writer.current.clone());
+}
+
+/// 当前注册表的快照，持有期间内容不会变化，不会阻塞写入方
+/// - 长时间的查询 (如合成求解) 应只取一次快照
+pub fn snapshot() -> Arc<Registry> {
+    CURRENT.load_full()
+}
+
+/// 注册表的可变引用，指向正在重载的暂存注册表或写入方的最新注册表
+pub struct RegistryMut<'a> {
+    pub items: &'a mut ItemManager,
+    pub recipes: &'a mut RecipeManager,
+    pub tags: &'a mut TagRegistry,
+}
+
+/// 修改写入方的最新注册表并在释放写入锁前发布，之后的 `snapshot` 能看到修改
+/// - 已发布的注册表仍被共享，每次修改会复制一次，批量修改应放在同一次调用中
+fn modify_latest<R>(writer: &mut Writer, f: impl FnOnce(RegistryMut) -> R) -> R {
+    let result = f(Arc::make_mut(&mut writer.current).as_mut());
+    publish(writer);
+    result
+}
+
+/// 修改注册表：重载进行中时修改暂存注册表，否则修改最新注册表
+pub fn modify<R>(f: impl FnOnce(RegistryMut) -> R) -> R {
+    let mut writer = WRITER.lock();
+    match writer.staging.as_mut() {
+        Some(staging) => f(staging.as_mut()),
+        None => modify_latest(&mut writer, f),
+    }
+}
+
+/// 修改最新注册表，不受重载影响，用于语言和ID映射等在重载之间保留的状态
+pub fn modify_current<R>(f: impl FnOnce(RegistryMut) -> R) -> R {
+    modify_latest(&mut WRITER.lock(), f)
+}
+
+/// 读取正在写入的注册表：重载进行中时为暂存注册表，否则为最新注册表
+pub fn read<R>(f: impl FnOnce(&Registry) -> R) -> R {
+    let writer = WRITER.lock();
+    match writer.staging.as_ref() {
+        Some(staging) => f(staging),
+        None => f(&writer.current),
+    }
+}
+
+/// 用 `f` 根据最新注册表构建的新注册表整体替换并立即发布，重载进行中时失败
+pub fn replace(f: impl FnOnce(&Registry) -> Result<Registry, String>) -> Result<(), String> {
+    let mut writer = WRITER.lock();
+    if writer.staging.is_some() {
+        return Err("A reload is in progress".to_string());
+    }
+    writer.current = Arc::new(f(&writer.current)?);
+    publish(&writer);
+    Ok(())
+}
+
+pub fn is_reloading() -> bool {
+    WRITER.lock().staging.is_some()
+}
+
+#[derive(Serialize)]
+pub struct RegistryInfo {
+    pub items: usize,
+    pub recipes: usize,
+    pub tags: usize,
+}
+
+/// 通知所有页面注册表已变化 (`ultralightui:registrychange` 事件，`detail` 为 `RegistryInfo`)
+/// - 重载进行中时不通知，提交时统一通知
+pub fn notify_registry_change() {
+    if is_reloading() {
+        return;
+    }
+    let registry = snapshot();
+    let info = RegistryInfo {
+        items: registry.items.items.len(),
+        recipes: registry.recipes.recipes().len(),
+        tags: registry.tags.iter().count(),
+    };
+    dispatch_event("ultralightui:registrychange", &info);
+}
+
+/// 开始重载：之后的注册操作写入一个空的暂存注册表，当前注册表保持不变直到提交
+/// - ID映射从当前注册表复制，已注册的名称保持原来的ID
+/// - 尚未提交的 FFI 配方引用的是旧注册表中的物品，全部放弃
+pub fn reload_begin() -> Result<(), String> {
+    let mut writer = WRITER.lock();
+    if writer.staging.is_some() {
+        return Err("A reload is already in progress".to_string());
+    }
+    let mut next = Registry::new();
+    next.items.id_map = writer.current.items.id_map.clone();
+    writer.staging = Some(next);
+    discard_pending();
+    Ok(())
+}
+
+/// 校验暂存注册表，没有错误 (或 `force`) 时替换当前注册表并返回校验报告
+/// - 校验失败时暂存注册表保留，可以继续修改后再次提交或放弃
+pub fn reload_commit(force: bool) -> Result<ValidationReport, String> {
+    let mut writer = WRITER.lock();
+    let Some(next) = writer.staging.as_ref() else {
+        return Err("No reload is in progress".to_string());
+    };
+    let report = validate(&next.items, next.recipes.recipes(), &next.tags);
+    if !report.errors.is_empty() && !force {
+        return Err(format!(
+            "Reloaded registry has {} errors: {}",
+            report.errors.len(),
+            serde_json::to_string(&report.errors).unwrap_or_default()
+        ));
+    }
+    let mut next = writer.staging.take().unwrap();
+    if let Some(map) = &mut next.items.id_map {
+        map.retire_missing(&next.items.names.keys().collect());
+    }
+    next.items.locales = writer.current.items.locales.clone();
+    next.items.rebuild_search();
+    writer.current = Arc::new(next);
+    publish(&writer);
+    drop(writer);
+    notify_registry_change();
+    Ok(report)
+}
+
+pub fn reload_abort() -> Result<(), String> {
+    WRITER
+        .lock()
+        .staging
+        .take()
+        .map(|_| ())
+        .ok_or_else(|| "No reload is in progress".to_string())
+}
+
+/// 移除物品，仍被配方引用的物品不能移除，物品同时从所有标签中移除
+pub fn remove_item(name: &ResourceLocation) -> Result<(), String> {
+    modify(|reg| {
+        let id = reg
+            .items
+            .id_by_name(name)
+            .ok_or_else(|| format!("Unknown item name '{}'", name))?;
+        // 只检查直接引用，通过标签使用的配方仍可使用标签中的其他物品
+        let used = reg.recipes.references(id).len();
+        if used > 0 {
+            return Err(format!("Item '{}' is still used by {} recipes", name, used));
+        }
+        reg.tags.remove_item(id);
+        reg.items.remove(name);
+        Ok(())
+    })?;
+    notify_registry_change();
+    Ok(())
+}
+
+pub fn remove_recipe(name: &ResourceLocation) -> Result<(), String> {
+    modify(|reg| {
+        reg.recipes
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| format!("Unknown recipe '{}'", name))
+    })?;
+    notify_registry_change();
+    Ok(())
+}
+
+/// 移除标签，仍被配方用作原料的标签不能移除
+pub fn remove_tag(tag: &ResourceLocation) -> Result<(), String> {
+    modify(|reg| {
+        let used = reg.recipes.tag_users(tag);
+        if used > 0 {
+            return Err(format!("Tag '#{}' is still used by {} recipes", tag, used));
+        }
+        reg.tags.remove(tag)
+    })?;
+    notify_registry_change();
+    Ok(())
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_reload_begin() -> u32 {
+    ffi_status(reload_begin())
+}
+
+/// 提交重载，返回 `ValidationReport` 的 JSON，`force` 非 0 时忽略校验错误
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_reload_commit(force: u32) -> usize {
+    ffi_json(reload_commit(force != 0))
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_reload_abort() -> u32 {
+    ffi_status(reload_abort())
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_remove_item(name: *const u8) -> u32 {
+    let name = read_c_string(name);
+    ffi_status(ResourceLocation::parse(name).and_then(|name| remove_item(&name)))
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_remove_recipe(name: *const u8) -> u32 {
+    let name = read_c_string(name);
+    ffi_status(ResourceLocation::parse(name).and_then(|name| remove_recipe(&name)))
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_remove_tag(tag: *const u8) -> u32 {
+    let tag = read_c_string(tag);
+    ffi_status(parse_tag(tag).and_then(|tag| remove_tag(&tag)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::{Item, ItemType, Recipe, RecipeInput};
+
+    fn item(name: &str) -> Item {
+        Item {
+            id: 0,
+            ty: ItemType::Solid,
+            name: ResourceLocation::parse(name).unwrap(),
+            i18n: name.to_string(),
+            l10n: String::new(),
+            max_stack: 64,
+            description: String::new(),
+        }
+    }
+
+    #[test]
+    fn item_used_only_in_layout_cannot_be_removed() {
+        let name = ResourceLocation::parse("test:layout_only").unwrap();
+        modify(|reg| {
+            let frame = reg.items.insert(item("test:layout_only")).unwrap();
+            let product = reg.items.insert(item("test:framed")).unwrap();
+            let mut recipe = Recipe::new();
+            recipe.add_product(product, 1).unwrap();
+            recipe.set_layout(1, 1).unwrap();
+            recipe.set_slot(0, 0, RecipeInput::Item(frame)).unwrap();
+            reg.recipes
+                .insert(ResourceLocation::parse("test:frame").unwrap(), recipe);
+        });
+
+        assert!(remove_item(&name).is_err());
+        assert!(snapshot().items.id_by_name(&name).is_some());
+    }
+}
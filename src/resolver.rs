@@ -0,0 +1,329 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::helper::ItemStacks;
+use crate::inventory::Inventory;
+use crate::items::{ItemManager, ItemType, Recipe};
+use crate::registry::snapshot;
+use crate::resource::ResourceLocation;
+use crate::tags::TagRegistry;
+use crate::{ffi_json, read_c_string};
+
+/// 合成树求解请求
+#[derive(Deserialize)]
+pub struct ResolveRequest {
+    /// 目标物品ID
+    pub item: u32,
+    /// 目标数量
+    pub count: u64,
+    /// 视为原材料、不再展开的物品
+    #[serde(default)]
+    pub raw: BTreeSet<u32>,
+    /// 指定某个物品使用的配方名称，未指定时使用名称排序最前的配方
+    #[serde(default)]
+    pub prefer: BTreeMap<u32, ResourceLocation>,
+    /// 指定标签原料使用的物品，未指定时使用标签中ID最小的物品
+    #[serde(default)]
+    pub prefer_tags: BTreeMap<ResourceLocation, u32>,
+}
+
+/// 合成树中的一个节点
+#[derive(Serialize)]
+pub struct CraftNode {
+    /// 物品ID
+    pub item: u32,
+    /// 需求数量
+    pub count: u64,
+    /// 由之前合成的余料或背包满足的数量
+    pub from_leftovers: u64,
+    /// 该节点来自标签原料时的标签名称
+    pub tag: Option<ResourceLocation>,
+    /// 是否在合成中消耗，不消耗的原料 (例如工具) 只需要一份并在合成后返还
+    pub consumed: bool,
+    /// 使用的配方名称，原材料为 `None`
+    pub recipe: Option<ResourceLocation>,
+    /// 配方执行次数
+    pub crafts: u64,
+    /// 配方的各项输入
+    pub inputs: Vec<CraftNode>,
+}
+
+/// 合成树求解结果
+#[derive(Serialize)]
+pub struct CraftPlan {
+    pub root: CraftNode,
+    /// 所需原材料总量
+    pub raw: ItemStacks,
+    /// 所需能量总量 (`ItemType::Energy` 物品)
+    pub energy: ItemStacks,
+    /// 合成结束后剩余的产物，包括 `available` 中未使用的物品
+    pub leftovers: ItemStacks,
+    /// 从 `available` 中取用的物品
+    pub from_inventory: ItemStacks,
+    /// 总耗时 (ticks)
+    pub timecost: u64,
+    /// 遇到的配方循环，每一项是从循环起点到重复物品的物品ID路径
+    pub cycles: Vec<Vec<u32>>,
+    /// 不包含任何物品、无法展开的标签原料及其数量
+    pub unresolved_tags: BTreeMap<ResourceLocation, u64>,
+}
+
+struct Resolver<'a> {
+    request: &'a ResolveRequest,
+    items: &'a ItemManager,
+    tags: &'a TagRegistry,
+    producers: BTreeMap<u32, Vec<(&'a ResourceLocation, &'a Recipe)>>,
+    raw: ItemStacks,
+    energy: ItemStacks,
+    leftovers: ItemStacks,
+    /// `available` 中尚未取用的物品，与合成产生的余料分开记录
+    inventory: ItemStacks,
+    from_inventory: ItemStacks,
+    timecost: u64,
+    cycles: Vec<Vec<u32>>,
+    unresolved_tags: BTreeMap<ResourceLocation, u64>,
+    stack: Vec<u32>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(
+        request: &'a ResolveRequest,
+        items: &'a ItemManager,
+        recipes: &'a BTreeMap<ResourceLocation, Recipe>,
+        tags: &'a TagRegistry,
+    ) -> Self {
+        let mut producers: BTreeMap<u32, Vec<_>> = BTreeMap::new();
+        for (name, recipe) in recipes {
+            for id in recipe.products.ids() {
+                if recipe.expected_output(id) > 0.0 {
+                    producers.entry(id).or_default().push((name, recipe));
+                }
+            }
+        }
+        Self {
+            request,
+            items,
+            tags,
+            producers,
+            raw: ItemStacks::new(),
+            energy: ItemStacks::new(),
+            leftovers: ItemStacks::new(),
+            inventory: ItemStacks::new(),
+            from_inventory: ItemStacks::new(),
+            timecost: 0,
+            cycles: Vec::new(),
+            unresolved_tags: BTreeMap::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    fn choose_recipe(&self, item: u32) -> Option<(&'a ResourceLocation, &'a Recipe)> {
+        let candidates = self.producers.get(&item)?;
+        if let Some(name) = self.request.prefer.get(&item)
+            && let Some(&found) = candidates.iter().find(|(n, _)| *n == name)
+        {
+            return Some(found);
+        }
+        candidates.first().copied()
+    }
+
+    fn expand(&mut self, item: u32, count: u64) -> CraftNode {
+        let is_energy = self
+            .items
+            .get_by_id(item)
+            .is_some_and(|item| item.ty == ItemType::Energy);
+        // 先使用合成产生的余料，再从背包中取
+        let from_leftovers = if is_energy {
+            0
+        } else {
+            let taken = self.leftovers.sub(item, count);
+            let from_inventory = self.inventory.sub(item, count - taken);
+            self.from_inventory.add(item, from_inventory);
+            taken + from_inventory
+        };
+        let need = count - from_leftovers;
+        let mut node = CraftNode {
+            item,
+            count,
+            from_leftovers,
+            tag: None,
+            consumed: true,
+            recipe: None,
+            crafts: 0,
+            inputs: Vec::new(),
+        };
+        if need == 0 {
+            return node;
+        }
+        if is_energy {
+            self.energy.add(item, need);
+            return node;
+        }
+
+        if let Some(pos) = self.stack.iter().position(|&id| id == item) {
+            let mut cycle = self.stack[pos..].to_vec();
+            cycle.push(item);
+            self.cycles.push(cycle);
+            self.raw.add(item, need);
+            return node;
+        }
+
+        let recipe = if self.request.raw.contains(&item) {
+            None
+        } else {
+            self.choose_recipe(item)
+        };
+        let Some((name, recipe)) = recipe else {
+            self.raw.add(item, need);
+            return node;
+        };
+
+        let crafts = crafts_needed(recipe, item, need);
+        node.recipe = Some(name.clone());
+        node.crafts = crafts;
+        self.timecost = self
+            .timecost
+            .saturating_add(recipe.timecost.saturating_mul(crafts));
+
+        self.stack.push(item);
+        let mut returned = Vec::new();
+        for (input, amount) in recipe.material.iter() {
+            if recipe.is_consumed(input) {
+                let child = self.expand(input, amount.saturating_mul(crafts));
+                node.inputs.push(child);
+            } else {
+                let mut child = self.expand(input, amount);
+                child.consumed = false;
+                node.inputs.push(child);
+                returned.push((input, amount));
+            }
+        }
+        for (tag, &amount) in recipe.material_tags.iter() {
+            let amount = amount.saturating_mul(crafts);
+            let prefer = self.request.prefer_tags.get(tag).copied();
+            match self.tags.representative(tag, prefer) {
+                Some(input) => {
+                    let mut child = self.expand(input, amount);
+                    child.tag = Some(tag.clone());
+                    node.inputs.push(child);
+                }
+                None => {
+                    let total = self.unresolved_tags.entry(tag.clone()).or_insert(0);
+                    *total = total.saturating_add(amount);
+                }
+            }
+        }
+        self.stack.pop();
+
+        for (input, amount) in returned {
+            self.leftovers.add(input, amount);
+        }
+        for product in recipe.products.ids() {
+            let produced = expected_amount(recipe, product, crafts);
+            let surplus = if product == item {
+                produced.saturating_sub(need)
+            } else {
+                produced
+            };
+            self.leftovers.add(product, surplus);
+        }
+
+        node
+    }
+}
+
+/// 浮点误差容限，避免 `7 / 0.7 = 10.000000000000002` 这类结果多取整一次
+const EPSILON: f64 = 1e-9;
+
+/// 多次合成的期望产量，按概率产出的部分向下取整
+fn expected_amount(recipe: &Recipe, product: u32, crafts: u64) -> u64 {
+    let amount = recipe.products.count(product);
+    let chance = recipe.chance(product);
+    if chance >= 1.0 {
+        amount.saturating_mul(crafts)
+    } else {
+        (amount as f64 * chance * crafts as f64 + EPSILON).floor() as u64
+    }
+}
+
+/// 期望产量 (`expected_amount`) 达到 `need` 的最少合成次数
+fn crafts_needed(recipe: &Recipe, product: u32, need: u64) -> u64 {
+    let amount = recipe.products.count(product);
+    let chance = recipe.chance(product);
+    if chance >= 1.0 {
+        return need.div_ceil(amount);
+    }
+    let mut crafts = (need as f64 / (amount as f64 * chance) - EPSILON).ceil() as u64;
+    // 估计值可能差一次，按 `expected_amount` 修正使两者一致
+    if expected_amount(recipe, product, crafts) < need {
+        crafts = crafts.saturating_add(1);
+    } else if crafts > 0 && expected_amount(recipe, product, crafts - 1) >= need {
+        crafts -= 1;
+    }
+    crafts
+}
+
+/// 将目标物品按配方递归展开为合成树
+/// - 多产物配方的副产物和多合成的部分计入余料，后续需求优先使用余料
+/// - 没有配方或被指定为原材料的物品作为叶子节点
+/// - 标签原料展开为标签中的一个物品，空标签在结果中单独列出
+/// - 按概率产出的产物以期望产量计算，不消耗的原料只需要一份
+/// - 能量类物品不展开，单独汇总
+/// - 遇到配方循环时在循环处停止展开，该物品按原材料计入，并在结果中报告循环路径
+pub fn resolve(
+    items: &ItemManager,
+    recipes: &BTreeMap<ResourceLocation, Recipe>,
+    tags: &TagRegistry,
+    request: &ResolveRequest,
+) -> Result<CraftPlan, String> {
+    resolve_with(items, recipes, tags, request, &Inventory::new())
+}
+
+/// 同 `resolve`，但需求可以由 `available` 中的物品满足 (优先使用合成产生的余料)，取用的数量记录在 `from_inventory`
+pub fn resolve_with(
+    items: &ItemManager,
+    recipes: &BTreeMap<ResourceLocation, Recipe>,
+    tags: &TagRegistry,
+    request: &ResolveRequest,
+    available: &Inventory,
+) -> Result<CraftPlan, String> {
+    if items.get_by_id(request.item).is_none() {
+        return Err(format!("Unknown item id {}", request.item));
+    }
+    if request.count == 0 {
+        return Err("Target quantity must be non-zero".to_string());
+    }
+    let mut resolver = Resolver::new(request, items, recipes, tags);
+    resolver.inventory = available.clone();
+    let root = resolver.expand(request.item, request.count);
+    resolver.leftovers.merge(&resolver.inventory);
+    Ok(CraftPlan {
+        root,
+        raw: resolver.raw,
+        energy: resolver.energy,
+        leftovers: resolver.leftovers,
+        from_inventory: resolver.from_inventory,
+        timecost: resolver.timecost,
+        cycles: resolver.cycles,
+        unresolved_tags: resolver.unresolved_tags,
+    })
+}
+
+/// 求解合成树，`request` 为 `ResolveRequest` 的 JSON，返回 `CraftPlan` 的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_resolve_crafting(request: *const u8) -> usize {
+    let request = read_c_string(request);
+    ffi_json(
+        serde_json::from_str::<ResolveRequest>(request)
+            .map_err(|e| format!("Invalid resolve request: {}", e))
+            .and_then(|request| {
+                let registry = snapshot();
+                resolve(
+                    &registry.items,
+                    registry.recipes.recipes(),
+                    &registry.tags,
+                    &request,
+                )
+            }),
+    )
+}
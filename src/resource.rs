@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// 省略命名空间时使用的默认命名空间
+pub const DEFAULT_NAMESPACE: &str = "minecraft";
+
+/// MC 的资源路径 (`namespace:path`)，用作物品和配方的注册名
+/// - 命名空间允许 `[a-z0-9_.-]`，路径额外允许 `/`
+/// - 解析时省略命名空间 (`stone` 或 `:stone`) 视为 `minecraft`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ResourceLocation {
+    namespace: String,
+    path: String,
+}
+
+fn is_valid_namespace_char(c: char) -> bool {
+    matches!(c, 'a'..='z' | '0'..='9' | '_' | '.' | '-')
+}
+
+pub fn is_valid_namespace(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(is_valid_namespace_char)
+}
+
+fn is_valid_path_char(c: char) -> bool {
+    is_valid_namespace_char(c) || c == '/'
+}
+
+fn check_chars(
+    text: &str,
+    part: &str,
+    offset: usize,
+    full: &str,
+    valid: fn(char) -> bool,
+) -> Result<(), String> {
+    match text.chars().enumerate().find(|&(_, c)| !valid(c)) {
+        Some((i, c)) => Err(format!(
+            "Invalid character '{}' at position {} in {} of resource location '{}'",
+            c,
+            offset + i,
+            part,
+            full
+        )),
+        None => Ok(()),
+    }
+}
+
+impl ResourceLocation {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (namespace, path, offset) = match text.split_once(':') {
+            Some((namespace, path)) => (namespace, path, namespace.chars().count() + 1),
+            None => ("", text, 0),
+        };
+        check_chars(namespace, "namespace", 0, text, is_valid_namespace_char)?;
+        check_chars(path, "path", offset, text, is_valid_path_char)?;
+        if path.is_empty() {
+            return Err(format!("Empty path in resource location '{}'", text));
+        }
+        let namespace = if namespace.is_empty() {
+            DEFAULT_NAMESPACE
+        } else {
+            namespace
+        };
+        Ok(Self {
+            namespace: namespace.to_string(),
+            path: path.to_string(),
+        })
+    }
+
+    /// 排在该命名空间所有资源路径之前的键，只用作 `BTreeMap::range` 的下界
+    pub fn namespace_start(namespace: &str) -> Self {
+        Self {
+            namespace: namespace.to_string(),
+            path: String::new(),
+        }
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl fmt::Display for ResourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.path)
+    }
+}
+
+impl FromStr for ResourceLocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Serialize for ResourceLocation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ResourceLocation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        Self::parse(&text).map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,211 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::items::{Item, ItemManager};
+use crate::registry::snapshot;
+use crate::resource::ResourceLocation;
+use crate::{ffi_json, read_c_string};
+
+/// 单个物品的预处理搜索文本，全部为小写
+#[derive(Clone)]
+struct SearchEntry {
+    name: String,
+    /// 注册名中冒号后的部分
+    path: String,
+    i18n: String,
+    l10n: String,
+    description: String,
+    /// 本地化名称的全拼 (不含中文时为空)
+    pinyin: String,
+    /// 本地化名称的拼音首字母 (不含中文时为空)
+    initials: String,
+}
+
+/// 物品搜索索引，随 `ItemManager::insert` 同步更新
+#[derive(Clone)]
+pub struct SearchIndex {
+    /// 物品ID -> 搜索文本
+    entries: BTreeMap<u32, SearchEntry>,
+}
+
+#[derive(Serialize)]
+pub struct SearchHit {
+    pub id: u32,
+    pub score: u32,
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{4e00}'..='\u{9fff}'
+        | '\u{3400}'..='\u{4dbf}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{20000}'..='\u{2a6df}')
+}
+
+/// 将中文转换为拼音全拼和首字母，其他字符原样保留
+fn to_pinyin(text: &str) -> (String, String) {
+    if !text.chars().any(is_cjk) {
+        return (String::new(), String::new());
+    }
+    let mut full = String::new();
+    let mut initials = String::new();
+    for c in text.chars() {
+        if is_cjk(c) {
+            if let Some(syllable) = deunicode::deunicode_char(c) {
+                let syllable = syllable.trim().to_lowercase();
+                initials.extend(syllable.chars().next());
+                full.push_str(&syllable);
+            }
+        } else if !c.is_whitespace() {
+            let c = c.to_lowercase();
+            full.extend(c.clone());
+            initials.extend(c);
+        }
+    }
+    (full, initials)
+}
+
+/// 精确、前缀、子串匹配的得分
+fn match_text(text: &str, query: &str) -> Option<u32> {
+    if text.is_empty() {
+        None
+    } else if text == query {
+        Some(100)
+    } else if text.starts_with(query) {
+        Some(80)
+    } else if text.contains(query) {
+        Some(60)
+    } else {
+        None
+    }
+}
+
+/// 子序列模糊匹配，匹配越紧凑得分越高 (最高 40)
+fn match_fuzzy(text: &str, query: &str) -> Option<u32> {
+    let mut chars = text.char_indices();
+    let mut start = None;
+    let mut end = 0;
+    for q in query.chars() {
+        let (i, c) = chars.find(|&(_, c)| c == q)?;
+        start.get_or_insert(i);
+        end = i + c.len_utf8();
+    }
+    let span = end - start.unwrap_or(0);
+    Some((40 * query.len() / span.max(1)).max(1) as u32)
+}
+
+impl SearchEntry {
+    fn new(item: &Item, l10n: &str) -> Self {
+        let (pinyin, initials) = to_pinyin(l10n);
+        Self {
+            name: item.name.to_string(),
+            path: item.name.path().to_string(),
+            i18n: item.i18n.to_lowercase(),
+            l10n: l10n.to_lowercase(),
+            description: item.description.to_lowercase(),
+            pinyin,
+            initials,
+        }
+    }
+
+    /// 单个查询词的得分，各字段按权重取最高分
+    fn score(&self, term: &str) -> Option<u32> {
+        let exact = [
+            (10, match_text(&self.l10n, term)),
+            (8, match_text(&self.name, term)),
+            (8, match_text(&self.path, term)),
+            (7, match_text(&self.pinyin, term)),
+            (6, match_text(&self.initials, term)),
+            (5, match_text(&self.i18n, term)),
+            (2, match_text(&self.description, term)),
+        ];
+        let fuzzy = [
+            (10, match_fuzzy(&self.l10n, term)),
+            (8, match_fuzzy(&self.name, term)),
+            (7, match_fuzzy(&self.pinyin, term)),
+        ];
+        exact
+            .into_iter()
+            .chain(fuzzy)
+            .filter_map(|(weight, score)| score.map(|s| weight * s))
+            .max()
+    }
+}
+
+impl SearchIndex {
+    pub const fn new() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// `l10n` 为物品在当前语言下的名称
+    pub fn insert(&mut self, item: &Item, l10n: &str) {
+        self.entries.insert(item.id, SearchEntry::new(item, l10n));
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        self.entries.remove(&id);
+    }
+
+    /// 单个小写查询词对指定物品的得分
+    pub fn score(&self, id: u32, term: &str) -> Option<u32> {
+        self.entries.get(&id)?.score(term)
+    }
+
+    /// 按空白拆分查询词，所有词都必须匹配，结果按得分从高到低排序
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let query = query.to_lowercase();
+        let terms: Vec<&str> = query.split_whitespace().collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+        let mut hits: Vec<SearchHit> = self
+            .entries
+            .iter()
+            .filter_map(|(&id, entry)| {
+                let mut total = 0;
+                for term in &terms {
+                    total += entry.score(term)?;
+                }
+                Some(SearchHit { id, score: total })
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.cmp(&a.score).then(a.id.cmp(&b.id)));
+        hits.truncate(limit);
+        hits
+    }
+}
+
+#[derive(Serialize)]
+pub struct SearchResult {
+    pub id: u32,
+    pub name: ResourceLocation,
+    pub l10n: String,
+    pub score: u32,
+}
+
+pub fn search_items(items: &ItemManager, query: &str, limit: usize) -> Vec<SearchResult> {
+    items
+        .search
+        .search(query, limit)
+        .into_iter()
+        .filter_map(|hit| {
+            let item = items.get_by_id(hit.id)?;
+            Some(SearchResult {
+                id: hit.id,
+                name: item.name.clone(),
+                l10n: items.l10n(item).to_string(),
+                score: hit.score,
+            })
+        })
+        .collect()
+}
+
+/// 搜索物品，返回 `SearchResult` 数组的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_search_items(query: *const u8, limit: u32) -> usize {
+    let query = read_c_string(query);
+    let registry = snapshot();
+    ffi_json(Ok(search_items(&registry.items, query, limit as usize)))
+}
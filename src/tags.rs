@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
+
+use crate::registry::{modify, snapshot};
+use crate::resource::ResourceLocation;
+use crate::{ffi_json, ffi_status, read_c_string};
+
+/// 物品标签，可以直接包含物品，也可以包含其他标签
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tag {
+    pub items: BTreeSet<u32>,
+    pub tags: BTreeSet<ResourceLocation>,
+}
+
+#[derive(Clone)]
+pub struct TagRegistry {
+    tags: BTreeMap<ResourceLocation, Tag>,
+    /// 物品ID -> 直接或间接包含该物品的标签，第一次查询时计算，标签变化时清空
+    item_tags: OnceLock<BTreeMap<u32, BTreeSet<ResourceLocation>>>,
+}
+
+/// 解析标签名，允许带 `#` 前缀
+pub fn parse_tag(text: &str) -> Result<ResourceLocation, String> {
+    ResourceLocation::parse(text.strip_prefix('#').unwrap_or(text))
+}
+
+impl TagRegistry {
+    pub const fn new() -> Self {
+        Self {
+            tags: BTreeMap::new(),
+            item_tags: OnceLock::new(),
+        }
+    }
+
+    fn invalidate(&mut self) {
+        self.item_tags = OnceLock::new();
+    }
+
+    pub fn add_item(&mut self, tag: ResourceLocation, item: u32) {
+        self.invalidate();
+        self.tags.entry(tag).or_default().items.insert(item);
+    }
+
+    pub fn add_tag(
+        &mut self,
+        tag: ResourceLocation,
+        child: ResourceLocation,
+    ) -> Result<(), String> {
+        if tag == child {
+            return Err(format!("Tag '#{}' cannot include itself", tag));
+        }
+        self.invalidate();
+        self.tags.entry(tag).or_default().tags.insert(child);
+        Ok(())
+    }
+
+    /// 替换标签的全部内容
+    pub fn set(&mut self, name: ResourceLocation, tag: Tag) {
+        self.invalidate();
+        self.tags.insert(name, tag);
+    }
+
+    /// 移除标签，其他标签对它的包含一并移除
+    pub fn remove(&mut self, tag: &ResourceLocation) -> Result<(), String> {
+        self.tags
+            .remove(tag)
+            .ok_or_else(|| format!("Unknown tag '#{}'", tag))?;
+        self.invalidate();
+        for entry in self.tags.values_mut() {
+            entry.tags.remove(tag);
+        }
+        Ok(())
+    }
+
+    /// 从所有标签中移除物品
+    pub fn remove_item(&mut self, item: u32) {
+        self.invalidate();
+        for entry in self.tags.values_mut() {
+            entry.items.remove(&item);
+        }
+    }
+
+    pub fn get(&self, tag: &ResourceLocation) -> Option<&Tag> {
+        self.tags.get(tag)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ResourceLocation, &Tag)> {
+        self.tags.iter()
+    }
+
+    /// 展开标签包含的所有物品，包括间接包含的标签，循环包含的标签只展开一次
+    pub fn resolve(&self, tag: &ResourceLocation) -> BTreeSet<u32> {
+        let mut items = BTreeSet::new();
+        let mut visited = BTreeSet::new();
+        let mut stack = vec![tag];
+        while let Some(tag) = stack.pop() {
+            if !visited.insert(tag) {
+                continue;
+            }
+            if let Some(entry) = self.tags.get(tag) {
+                items.extend(&entry.items);
+                stack.extend(&entry.tags);
+            }
+        }
+        items
+    }
+
+    /// 直接或间接包含该物品的所有标签，按名称排序
+    pub fn tags_of(&self, item: u32) -> impl Iterator<Item = &ResourceLocation> {
+        let item_tags = self.item_tags.get_or_init(|| {
+            let mut item_tags: BTreeMap<u32, BTreeSet<ResourceLocation>> = BTreeMap::new();
+            for name in self.tags.keys() {
+                for id in self.resolve(name) {
+                    item_tags.entry(id).or_default().insert(name.clone());
+                }
+            }
+            item_tags
+        });
+        item_tags.get(&item).into_iter().flatten()
+    }
+
+    /// 为标签原料选择一个代表物品：优先使用 `prefer`，否则取ID最小的物品
+    pub fn representative(&self, tag: &ResourceLocation, prefer: Option<u32>) -> Option<u32> {
+        let items = self.resolve(tag);
+        match prefer {
+            Some(id) if items.contains(&id) => Some(id),
+            _ => items.first().copied(),
+        }
+    }
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_tag_add_item(tag: *const u8, item: u32) -> u32 {
+    let tag = read_c_string(tag);
+    ffi_status(parse_tag(tag).and_then(|tag| {
+        modify(|reg| {
+            if reg.items.get_by_id(item).is_none() {
+                return Err(format!("Unknown item id {}", item));
+            }
+            reg.tags.add_item(tag, item);
+            Ok(())
+        })
+    }))
+}
+
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_tag_add_item_by_name(tag: *const u8, name: *const u8) -> u32 {
+    let tag = read_c_string(tag);
+    let name = read_c_string(name);
+    ffi_status(parse_tag(tag).and_then(|tag| {
+        let name = ResourceLocation::parse(name)?;
+        modify(|reg| {
+            let id = reg
+                .items
+                .id_by_name(&name)
+                .ok_or_else(|| format!("Unknown item name '{}'", name))?;
+            reg.tags.add_item(tag, id);
+            Ok(())
+        })
+    }))
+}
+
+/// 让 `tag` 包含 `child` 标签中的所有物品
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_tag_add_tag(tag: *const u8, child: *const u8) -> u32 {
+    let tag = read_c_string(tag);
+    let child = read_c_string(child);
+    ffi_status(parse_tag(tag).and_then(|tag| {
+        let child = parse_tag(child)?;
+        modify(|reg| reg.tags.add_tag(tag, child))
+    }))
+}
+
+/// 返回所有标签名称数组的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_list_tags() -> usize {
+    let registry = snapshot();
+    ffi_json(Ok(registry
+        .tags
+        .iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>()))
+}
+
+/// 返回标签展开后的物品ID数组的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_get_tag_items(tag: *const u8) -> usize {
+    let tag = read_c_string(tag);
+    ffi_json(parse_tag(tag).map(|tag| snapshot().tags.resolve(&tag)))
+}
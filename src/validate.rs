@@ -0,0 +1,384 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::items::{ItemManager, ItemType, Recipe, RecipeInput};
+use crate::registry::snapshot;
+use crate::resource::ResourceLocation;
+use crate::tags::TagRegistry;
+use crate::{ffi_json, ffi_status, read_c_string};
+
+/// 校验发现的一个问题
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Issue {
+    /// 配方引用了不存在的物品ID，`role` 为引用位置
+    DanglingItem {
+        recipe: ResourceLocation,
+        item: u32,
+        role: &'static str,
+    },
+    /// 配方中数量为 0 的条目
+    ZeroQuantity {
+        recipe: ResourceLocation,
+        entry: String,
+        role: &'static str,
+    },
+    /// 配方没有产物或没有任何输入
+    EmptyRecipe {
+        recipe: ResourceLocation,
+        missing: &'static str,
+    },
+    /// 配方的产物与输入完全相同
+    SelfRecipe { recipe: ResourceLocation },
+    /// 配方使用的标签不包含任何物品
+    EmptyTag {
+        recipe: ResourceLocation,
+        tag: ResourceLocation,
+    },
+    /// 固体物品的最大堆叠数量为 0
+    InvalidMaxStack { item: u32, max_stack: u64 },
+    /// 有配方产出、但所有配方的输入都无法获得的物品
+    UnreachableItem { item: u32 },
+    /// 输入和产物完全相同的多个配方
+    DuplicateRecipe { recipes: Vec<ResourceLocation> },
+    /// 配方图中相互依赖的一组物品
+    Cycle { items: Vec<u32> },
+}
+
+/// 校验报告，`errors` 会导致计算结果错误，`warnings` 可能是有意为之
+#[derive(Serialize, Default)]
+pub struct ValidationReport {
+    pub items: usize,
+    pub recipes: usize,
+    pub errors: Vec<Issue>,
+    pub warnings: Vec<Issue>,
+}
+
+/// 用于判断重复配方的键，概率按位比较
+type RecipeKey = (
+    Option<ResourceLocation>,
+    Vec<(u32, u64)>,
+    Vec<(ResourceLocation, u64)>,
+    Vec<(u32, u64)>,
+    Vec<(u32, u64)>,
+);
+
+fn sorted<K: Ord + Clone, V: Copy>(entries: impl Iterator<Item = (K, V)>) -> Vec<(K, V)> {
+    let mut entries: Vec<_> = entries.collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
+fn recipe_key(recipe: &Recipe) -> RecipeKey {
+    (
+        recipe.category.clone(),
+        recipe.material.iter().collect(),
+        sorted(recipe.material_tags.iter().map(|(t, &c)| (t.clone(), c))),
+        recipe.products.iter().collect(),
+        sorted(
+            recipe
+                .product_chances
+                .iter()
+                .map(|(&id, &c)| (id, c.to_bits())),
+        ),
+    )
+}
+
+fn check_recipe(
+    items: &ItemManager,
+    tags: &TagRegistry,
+    name: &ResourceLocation,
+    recipe: &Recipe,
+    report: &mut ValidationReport,
+) {
+    let mut referenced: Vec<(u32, &'static str)> = Vec::new();
+    referenced.extend(recipe.material.ids().map(|id| (id, "material")));
+    referenced.extend(recipe.products.ids().map(|id| (id, "product")));
+    referenced.extend(recipe.catalysts.iter().map(|&id| (id, "catalyst")));
+    if let Some(layout) = &recipe.layout {
+        referenced.extend(layout.slots.iter().filter_map(|slot| match slot {
+            Some(RecipeInput::Item(id)) => Some((*id, "layout")),
+            _ => None,
+        }));
+    }
+    for (item, role) in referenced {
+        if items.get_by_id(item).is_none() {
+            report.errors.push(Issue::DanglingItem {
+                recipe: name.clone(),
+                item,
+                role,
+            });
+        }
+    }
+
+    let zero_items = [
+        ("material", &recipe.material),
+        ("product", &recipe.products),
+    ]
+    .into_iter()
+    .flat_map(|(role, entries)| {
+        entries
+            .iter()
+            .filter(|&(_, c)| c == 0)
+            .map(move |(id, _)| (id.to_string(), role))
+    });
+    let zero_tags = recipe
+        .material_tags
+        .iter()
+        .filter(|&(_, &c)| c == 0)
+        .map(|(tag, _)| (format!("#{}", tag), "material"));
+    for (entry, role) in zero_items.chain(zero_tags) {
+        report.errors.push(Issue::ZeroQuantity {
+            recipe: name.clone(),
+            entry,
+            role,
+        });
+    }
+
+    if recipe.products.is_empty() {
+        report.errors.push(Issue::EmptyRecipe {
+            recipe: name.clone(),
+            missing: "products",
+        });
+    }
+    if recipe.material.is_empty() && recipe.material_tags.len() == 0 {
+        report.warnings.push(Issue::EmptyRecipe {
+            recipe: name.clone(),
+            missing: "materials",
+        });
+    } else if recipe.material_tags.len() == 0 && recipe.material == recipe.products {
+        report.warnings.push(Issue::SelfRecipe {
+            recipe: name.clone(),
+        });
+    }
+
+    for (tag, _) in recipe.material_tags.iter() {
+        if tags.resolve(tag).is_empty() {
+            report.warnings.push(Issue::EmptyTag {
+                recipe: name.clone(),
+                tag: tag.clone(),
+            });
+        }
+    }
+}
+
+/// 物品 -> 该物品作为输入的配方产出的物品
+fn dependency_graph(
+    recipes: &BTreeMap<ResourceLocation, Recipe>,
+    tags: &TagRegistry,
+) -> BTreeMap<u32, BTreeSet<u32>> {
+    let mut graph: BTreeMap<u32, BTreeSet<u32>> = BTreeMap::new();
+    for recipe in recipes.values() {
+        let inputs = recipe.material.ids().chain(
+            recipe
+                .material_tags
+                .iter()
+                .flat_map(|(tag, _)| tags.resolve(tag)),
+        );
+        for input in inputs {
+            let products = graph.entry(input).or_default();
+            products.extend(recipe.products.ids());
+        }
+    }
+    graph
+}
+
+/// Tarjan 强连通分量，返回包含多个物品或有自环的分量
+fn find_cycles(graph: &BTreeMap<u32, BTreeSet<u32>>) -> Vec<Vec<u32>> {
+    struct State {
+        index: BTreeMap<u32, usize>,
+        low: BTreeMap<u32, usize>,
+        stack: Vec<u32>,
+        on_stack: BTreeSet<u32>,
+        cycles: Vec<Vec<u32>>,
+    }
+
+    impl State {
+        fn enter(&mut self, node: u32) {
+            let index = self.index.len();
+            self.index.insert(node, index);
+            self.low.insert(node, index);
+            self.stack.push(node);
+            self.on_stack.insert(node);
+        }
+
+        fn lower(&mut self, node: u32, value: usize) {
+            let low = self.low[&node].min(value);
+            self.low.insert(node, low);
+        }
+    }
+
+    let successors = |node: u32| graph.get(&node).into_iter().flatten().copied();
+    let mut state = State {
+        index: BTreeMap::new(),
+        low: BTreeMap::new(),
+        stack: Vec::new(),
+        on_stack: BTreeSet::new(),
+        cycles: Vec::new(),
+    };
+    for &root in graph.keys() {
+        if state.index.contains_key(&root) {
+            continue;
+        }
+        // 用显式栈代替递归，很长的配方链也不会耗尽调用栈
+        state.enter(root);
+        let mut calls = vec![(root, successors(root))];
+        while let Some((node, next)) = calls.last_mut() {
+            let node = *node;
+            if let Some(next) = next.next() {
+                if !state.index.contains_key(&next) {
+                    state.enter(next);
+                    calls.push((next, successors(next)));
+                } else if state.on_stack.contains(&next) {
+                    state.lower(node, state.index[&next]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(parent, _)) = calls.last() {
+                state.lower(parent, state.low[&node]);
+            }
+            if state.low[&node] == state.index[&node] {
+                let mut component = Vec::new();
+                while let Some(top) = state.stack.pop() {
+                    state.on_stack.remove(&top);
+                    component.push(top);
+                    if top == node {
+                        break;
+                    }
+                }
+                let self_loop = graph.get(&node).is_some_and(|n| n.contains(&node));
+                if component.len() > 1 || self_loop {
+                    component.sort();
+                    state.cycles.push(component);
+                }
+            }
+        }
+    }
+    state.cycles
+}
+
+/// 从没有配方产出的物品出发，反复加入输入都可获得的配方的产物
+fn reachable_items(
+    items: &ItemManager,
+    recipes: &BTreeMap<ResourceLocation, Recipe>,
+    tags: &TagRegistry,
+) -> BTreeSet<u32> {
+    let produced: BTreeSet<u32> = recipes.values().flat_map(|r| r.products.ids()).collect();
+    let mut reachable: BTreeSet<u32> = items
+        .items
+        .iter()
+        .map(|item| item.id)
+        .filter(|id| !produced.contains(id))
+        .collect();
+    // 标签内容在循环中不变，只展开一次
+    let tag_items: BTreeMap<&ResourceLocation, BTreeSet<u32>> = recipes
+        .values()
+        .flat_map(|recipe| recipe.material_tags.iter().map(|(tag, _)| tag))
+        .map(|tag| (tag, tags.resolve(tag)))
+        .collect();
+    loop {
+        let mut changed = false;
+        for recipe in recipes.values() {
+            let ready = recipe
+                .material
+                .ids()
+                .chain(recipe.catalysts.iter().copied())
+                .all(|id| reachable.contains(&id))
+                && recipe
+                    .material_tags
+                    .iter()
+                    .all(|(tag, _)| tag_items[tag].iter().any(|id| reachable.contains(id)));
+            if ready {
+                for id in recipe.products.ids() {
+                    changed |= reachable.insert(id);
+                }
+            }
+        }
+        if !changed {
+            return reachable;
+        }
+    }
+}
+
+/// 校验物品表和配方表，返回所有发现的问题
+pub fn validate(
+    items: &ItemManager,
+    recipes: &BTreeMap<ResourceLocation, Recipe>,
+    tags: &TagRegistry,
+) -> ValidationReport {
+    let mut report = ValidationReport {
+        items: items.items.len(),
+        recipes: recipes.len(),
+        ..Default::default()
+    };
+
+    for item in &items.items {
+        if item.ty == ItemType::Solid && item.max_stack == 0 {
+            report.errors.push(Issue::InvalidMaxStack {
+                item: item.id,
+                max_stack: item.max_stack,
+            });
+        }
+    }
+
+    let mut duplicates: BTreeMap<RecipeKey, Vec<ResourceLocation>> = BTreeMap::new();
+    for (name, recipe) in recipes {
+        check_recipe(items, tags, name, recipe, &mut report);
+        duplicates
+            .entry(recipe_key(recipe))
+            .or_default()
+            .push(name.clone());
+    }
+    for names in duplicates.into_values().filter(|names| names.len() > 1) {
+        report
+            .warnings
+            .push(Issue::DuplicateRecipe { recipes: names });
+    }
+
+    let reachable = reachable_items(items, recipes, tags);
+    for item in &items.items {
+        if !reachable.contains(&item.id) {
+            report
+                .warnings
+                .push(Issue::UnreachableItem { item: item.id });
+        }
+    }
+
+    for items in find_cycles(&dependency_graph(recipes, tags)) {
+        report.warnings.push(Issue::Cycle { items });
+    }
+    report
+}
+
+/// 校验注册表，返回 `ValidationReport` 的 JSON
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_validate_registry() -> usize {
+    let registry = snapshot();
+    ffi_json(Ok(validate(
+        &registry.items,
+        registry.recipes.recipes(),
+        &registry.tags,
+    )))
+}
+
+/// 校验注册表并将报告写入 JSON 文件
+#[unsafe(no_mangle)]
+extern "C" fn ultralightui_export_validation_report(path: *const u8) -> u32 {
+    let path = Path::new(read_c_string(path));
+    let report = {
+        let registry = snapshot();
+        validate(&registry.items, registry.recipes.recipes(), &registry.tags)
+    };
+    ffi_status(
+        File::create(path)
+            .map_err(|e| format!("Failed to create '{}': {}", path.display(), e))
+            .and_then(|file| {
+                serde_json::to_writer_pretty(BufWriter::new(file), &report)
+                    .map_err(|e| format!("Failed to write report to '{}': {}", path.display(), e))
+            }),
+    )
+}
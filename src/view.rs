@@ -7,6 +7,7 @@ use ul_next::event::{MouseButton, MouseEvent, MouseEventType, ScrollEvent, Scrol
 use ul_next::key_code::VirtualKeyCode;
 use ul_next::view::ViewConfig;
 
+use crate::js::install_api;
 use crate::render::{GL_RENDERER, UL_RENDERER, renderer_pending, renderer_run};
 use crate::{LIB, read_c_string};
 
@@ -39,6 +40,7 @@ extern "C" fn ultralightui_create_view(
             .create_view(width, height, &view_config, None)
             .unwrap();
 
+        install_api(&view);
         view.load_url(&url).unwrap();
 
         let id = views.len() as u32;